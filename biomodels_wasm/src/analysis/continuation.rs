@@ -0,0 +1,370 @@
+use nalgebra::{DMatrix, DVector};
+use serde::{Serialize, Deserialize};
+
+use crate::models::BioModelData;
+use crate::simulator::{Simulator, SteadyStateOptions};
+
+use super::stability;
+
+/// Corrector tolerance on the norm of the extended residual.
+const CORRECTOR_TOLERANCE: f64 = 1e-8;
+const CORRECTOR_ITERATIONS: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ContinuationOptions {
+    /// Parameter interval the branch is followed within. Defaults to a
+    /// decade either side of the parameter's current value, or ±1 around it
+    /// when the value is not positive.
+    pub min_value: Option<f64>,
+    pub max_value: Option<f64>,
+    /// Initial, smallest and largest arclength step.
+    pub step: f64,
+    pub min_step: f64,
+    pub max_step: f64,
+    pub max_points: usize,
+    /// +1 to start towards larger parameter values, -1 towards smaller.
+    pub direction: f64,
+}
+
+impl Default for ContinuationOptions {
+    fn default() -> Self {
+        ContinuationOptions {
+            min_value: None,
+            max_value: None,
+            step: 0.01,
+            min_step: 1e-6,
+            max_step: 0.1,
+            max_points: 500,
+            direction: 1.0,
+        }
+    }
+}
+
+impl ContinuationOptions {
+    fn range_around(&self, p0: f64) -> (f64, f64) {
+        let (low, high) = if p0 > 0.0 { (p0 / 10.0, p0 * 10.0) } else { (p0 - 1.0, p0 + 1.0) };
+        (self.min_value.unwrap_or(low), self.max_value.unwrap_or(high))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchPoint {
+    pub parameter: f64,
+    pub state: Vec<f64>,
+    pub stable: bool,
+    pub classification: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bifurcation {
+    /// "fold" or "hopf".
+    pub kind: String,
+    /// Parameter value at the detecting branch point for folds; linearly
+    /// interpolated between the bracketing points for Hopf bifurcations.
+    pub parameter: f64,
+    pub point_index: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContinuationResult {
+    pub parameter_id: String,
+    pub species_ids: Vec<String>,
+    pub points: Vec<BranchPoint>,
+    pub bifurcations: Vec<Bifurcation>,
+}
+
+/// Follows the steady-state branch through `param_id` with
+/// pseudo-arclength continuation.
+///
+/// Conserved totals are held at their values in the initial state, so the
+/// extended system stays regular. Folds show up as a sign change in the
+/// parameter component of the tangent and Hopf points as a complex pair
+/// crossing the imaginary axis.
+pub fn continuation(
+    simulator: &Simulator,
+    model: &BioModelData,
+    param_id: &str,
+    options: &ContinuationOptions,
+) -> Result<ContinuationResult, String> {
+    let mut sim = simulator.clone();
+    let p0 = sim.parameter_value(param_id)
+        .ok_or_else(|| format!("Parameter '{}' not found", param_id))?;
+    sim.check_rate_parameter(param_id)?;
+    
+    let (min_value, max_value) = options.range_around(p0);
+    if !(min_value <= p0 && p0 <= max_value) {
+        return Err(format!(
+            "{} = {} lies outside the continuation interval [{}, {}]",
+            param_id, p0, min_value, max_value
+        ));
+    }
+    
+    let start = sim.steady_state(&SteadyStateOptions::default())?;
+    if !start.converged {
+        return Err(format!(
+            "No steady state found at {} = {} (residual {:.3e})",
+            param_id, p0, start.residual_norm
+        ));
+    }
+    
+    let n = start.state.len();
+    let conserved = sim.left_null_space();
+    let x0 = DVector::from_column_slice(&start.state);
+    let totals = conserved.transpose() * &x0;
+    
+    let mut y = x0.clone().resize_vertically(n + 1, p0);
+    let mut tangent = null_vector(&extended_jacobian(&mut sim, param_id, &y, &conserved));
+    if tangent[n] * options.direction < 0.0 {
+        tangent = -tangent;
+    }
+    
+    let mut points = vec![branch_point(&mut sim, model, param_id, &y)?];
+    let mut bifurcations = Vec::new();
+    let mut ds = options.step;
+    
+    while points.len() < options.max_points {
+        let predicted = &y + &tangent * ds;
+        
+        let corrected = correct(&mut sim, param_id, &predicted, &tangent, &conserved, &totals);
+        let (next, iterations) = match corrected {
+            Some(result) => result,
+            None => {
+                ds /= 2.0;
+                if ds < options.min_step {
+                    break;
+                }
+                continue;
+            }
+        };
+        
+        let p = next[n];
+        if p < min_value || p > max_value {
+            break;
+        }
+        
+        let next_tangent = match oriented_tangent(&extended_jacobian(&mut sim, param_id, &next, &conserved), &tangent) {
+            Some(t) => t,
+            None => break,
+        };
+        
+        let point = branch_point(&mut sim, model, param_id, &next)?;
+        let index = points.len();
+        
+        if tangent[n] * next_tangent[n] < 0.0 {
+            bifurcations.push(Bifurcation {
+                kind: "fold".to_string(),
+                parameter: p,
+                point_index: index,
+            });
+        }
+        
+        let previous_real = leading_complex_real(&mut sim, param_id, &y);
+        let current_real = leading_complex_real(&mut sim, param_id, &next);
+        if let (Some(a), Some(b)) = (previous_real, current_real) {
+            if a * b < 0.0 {
+                let fraction = a / (a - b);
+                bifurcations.push(Bifurcation {
+                    kind: "hopf".to_string(),
+                    parameter: y[n] + fraction * (p - y[n]),
+                    point_index: index,
+                });
+            }
+        }
+        
+        points.push(point);
+        y = next;
+        tangent = next_tangent;
+        
+        if iterations <= 3 {
+            ds = (ds * 1.5).min(options.max_step);
+        }
+    }
+    
+    Ok(ContinuationResult {
+        parameter_id: param_id.to_string(),
+        species_ids: model.species.iter().map(|s| s.id.clone()).collect(),
+        points,
+        bifurcations,
+    })
+}
+
+/// Newton corrector on the steady-state equations, the conservation
+/// constraints and the pseudo-arclength condition `t · (y - y_pred) = 0`.
+fn correct(
+    sim: &mut Simulator,
+    param_id: &str,
+    predicted: &DVector<f64>,
+    tangent: &DVector<f64>,
+    conserved: &DMatrix<f64>,
+    totals: &DVector<f64>,
+) -> Option<(DVector<f64>, usize)> {
+    let n = predicted.len() - 1;
+    let m = conserved.ncols();
+    let mut y = predicted.clone();
+    
+    for iteration in 0..CORRECTOR_ITERATIONS {
+        let x = y.rows(0, n).into_owned();
+        sim.set_parameter_value(param_id, y[n]).ok()?;
+        
+        let mut residual = DVector::zeros(n + m + 1);
+        residual.rows_mut(0, n).copy_from(&sim.compute_derivatives(&x));
+        residual.rows_mut(n, m).copy_from(&(conserved.transpose() * &x - totals));
+        residual[n + m] = tangent.dot(&(&y - predicted));
+        
+        if residual.norm() < CORRECTOR_TOLERANCE {
+            return Some((y, iteration));
+        }
+        
+        let mut system = extended_jacobian(sim, param_id, &y, conserved).resize_vertically(n + m + 1, 0.0);
+        system.row_mut(n + m).copy_from(&tangent.transpose());
+        
+        let step = system.svd(true, true).solve(&(-residual), 1e-12).ok()?;
+        y += step;
+        if !y.iter().all(|v| v.is_finite()) {
+            return None;
+        }
+    }
+    
+    None
+}
+
+/// Jacobian of the steady-state equations and conservation constraints
+/// with respect to the state and the continuation parameter.
+fn extended_jacobian(sim: &mut Simulator, param_id: &str, y: &DVector<f64>, conserved: &DMatrix<f64>) -> DMatrix<f64> {
+    let n = y.len() - 1;
+    let m = conserved.ncols();
+    let x = y.rows(0, n).into_owned();
+    let p = y[n];
+    
+    let mut jacobian = DMatrix::zeros(n + m, n + 1);
+    
+    let _ = sim.set_parameter_value(param_id, p);
+    jacobian.view_mut((0, 0), (n, n)).copy_from(&sim.jacobian(&x));
+    let f0 = sim.compute_derivatives(&x);
+    
+    let h = f64::EPSILON.sqrt() * p.abs().max(1.0);
+    let _ = sim.set_parameter_value(param_id, p + h);
+    let f_p = (sim.compute_derivatives(&x) - f0) / h;
+    let _ = sim.set_parameter_value(param_id, p);
+    jacobian.view_mut((0, n), (n, 1)).copy_from(&f_p);
+    
+    jacobian.view_mut((n, 0), (m, n)).copy_from(&conserved.transpose());
+    jacobian
+}
+
+/// Unit vector spanning the (numerical) null space of `matrix`.
+fn null_vector(matrix: &DMatrix<f64>) -> DVector<f64> {
+    let eigen = (matrix.transpose() * matrix).symmetric_eigen();
+    let smallest = eigen.eigenvalues.iter()
+        .enumerate()
+        .min_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    eigen.eigenvectors.column(smallest).normalize()
+}
+
+/// Tangent at a new branch point: solving `[J; t_prev] v = [0; 1]` keeps
+/// the orientation of the previous tangent and avoids forming `JᵀJ`.
+fn oriented_tangent(jacobian: &DMatrix<f64>, previous: &DVector<f64>) -> Option<DVector<f64>> {
+    let rows = jacobian.nrows();
+    let mut system = jacobian.clone().resize_vertically(rows + 1, 0.0);
+    system.row_mut(rows).copy_from(&previous.transpose());
+    let mut rhs = DVector::zeros(rows + 1);
+    rhs[rows] = 1.0;
+    
+    let tangent = system.svd(true, true).solve(&rhs, 1e-12).ok()?;
+    let norm = tangent.norm();
+    if norm > 0.0 && norm.is_finite() {
+        Some(tangent / norm)
+    } else {
+        None
+    }
+}
+
+fn branch_point(sim: &mut Simulator, model: &BioModelData, param_id: &str, y: &DVector<f64>) -> Result<BranchPoint, String> {
+    let n = y.len() - 1;
+    sim.set_parameter_value(param_id, y[n])?;
+    let state = y.rows(0, n).as_slice().to_vec();
+    let report = stability(sim, model, &state)?;
+    
+    Ok(BranchPoint {
+        parameter: y[n],
+        state,
+        stable: report.classification == "stable",
+        classification: report.classification,
+    })
+}
+
+/// Largest real part among eigenvalues with non-zero imaginary part.
+fn leading_complex_real(sim: &mut Simulator, param_id: &str, y: &DVector<f64>) -> Option<f64> {
+    let n = y.len() - 1;
+    sim.set_parameter_value(param_id, y[n]).ok()?;
+    let jacobian = sim.jacobian(&y.rows(0, n).into_owned());
+    jacobian.complex_eigenvalues().iter()
+        .filter(|z| z.im.abs() > 1e-9 * z.norm().max(1.0))
+        .map(|z| z.re)
+        .reduce(f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::infix::parse_infix;
+    use crate::models::testing::mass_action;
+    use crate::models::Parameter;
+    
+    /// dX/dt = p - (X - 1)², with equilibria X = 1 ± √p that meet in a
+    /// saddle-node at p = 0.
+    fn saddle_node() -> BioModelData {
+        let mut model = mass_action(&[("X", 2.0)], &[("", "X", 0.0)]);
+        model.parameters.push(Parameter { id: "p".to_string(), value: 1.0, constant: true });
+        model.reactions[0].kinetic_math = Some(parse_infix("p - (X - 1)^2").unwrap());
+        model
+    }
+    
+    #[test]
+    fn newton_starts_on_the_upper_branch() {
+        let model = saddle_node();
+        let start = Simulator::new(&model).steady_state(&SteadyStateOptions::default()).unwrap();
+        assert!(start.converged);
+        assert!((start.state[0] - 2.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn the_branch_starts_where_simulate_settles() {
+        let mut model = saddle_node();
+        model.species[0].initial_concentration = 3.0;
+        let settled = Simulator::new(&model).simulate(50.0, 0.05, "rk4").unwrap();
+        let x = settled.values[settled.values.len() - 1];
+        
+        let result = continuation(&Simulator::new(&model), &model, "p", &ContinuationOptions::default()).unwrap();
+        assert!((result.points[0].state[0] - x).abs() < 1e-6, "branch at {}, simulate at {}", result.points[0].state[0], x);
+    }
+    
+    #[test]
+    fn detects_the_fold_of_a_saddle_node() {
+        let model = saddle_node();
+        let options = ContinuationOptions {
+            min_value: Some(-1.0),
+            max_value: Some(1.0),
+            direction: -1.0,
+            ..Default::default()
+        };
+        let result = continuation(&Simulator::new(&model), &model, "p", &options).unwrap();
+        
+        let folds: Vec<&Bifurcation> = result.bifurcations.iter().filter(|b| b.kind == "fold").collect();
+        assert_eq!(folds.len(), 1);
+        assert!(folds[0].parameter.abs() < 0.01, "fold at p = {}", folds[0].parameter);
+        
+        // Every point lies on X = 1 ± √p, stable above the fold and
+        // unstable below it.
+        for point in &result.points {
+            let x = point.state[0];
+            assert!((point.parameter - (x - 1.0).powi(2)).abs() < 1e-6);
+            assert_eq!(point.stable, x > 1.0, "X = {}", x);
+        }
+        let last = result.points.last().unwrap();
+        assert!(last.state[0] < 1.0 && last.parameter > 0.5);
+    }
+}
@@ -0,0 +1,147 @@
+use nalgebra::{Complex, DMatrix, DVector};
+use serde::{Serialize, Deserialize};
+
+use crate::models::BioModelData;
+use crate::simulator::Simulator;
+
+pub mod continuation;
+pub mod pk;
+
+pub use continuation::{continuation, ContinuationOptions, ContinuationResult};
+pub use pk::{pk_summary, AucMethod, DoseInterval, PkMetrics, PkOptions};
+
+/// Real parts smaller than this (relative to the largest eigenvalue) are
+/// treated as zero.
+const ZERO_TOLERANCE: f64 = 1e-9;
+
+/// Species listed per eigenvector.
+const DOMINANT_SPECIES: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StabilityReport {
+    /// "stable", "unstable" or "marginal", ignoring the zero eigenvalues
+    /// that come from conservation laws.
+    pub classification: String,
+    /// True when a complex pair exists, i.e. perturbations spiral.
+    pub oscillatory: bool,
+    pub modes: Vec<Mode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mode {
+    pub real: f64,
+    pub imag: f64,
+    /// 1/|Re(λ)|: the e-folding time of this mode. Infinite for zero modes.
+    pub timescale: f64,
+    /// 2π/|Im(λ)| for oscillatory modes.
+    pub period: Option<f64>,
+    /// Whether this zero mode is accounted for by a conservation law.
+    pub conserved: bool,
+    /// Species with the largest eigenvector components and their share of
+    /// the (unit-norm) eigenvector.
+    pub dominant_species: Vec<(String, f64)>,
+}
+
+/// Linearises the model at `state` and classifies the equilibrium from the
+/// eigenvalues of the Jacobian.
+pub fn stability(simulator: &Simulator, model: &BioModelData, state: &[f64]) -> Result<StabilityReport, String> {
+    if state.len() != model.species.len() {
+        return Err(format!(
+            "State has {} entries but the model has {} species",
+            state.len(), model.species.len()
+        ));
+    }
+    
+    if state.iter().any(|x| !x.is_finite()) {
+        return Err("State must be finite".to_string());
+    }
+    
+    // The eigenvalue iteration does not terminate on NaN or infinite entries.
+    let jacobian = simulator.jacobian(&DVector::from_column_slice(state));
+    if jacobian.iter().any(|x| !x.is_finite()) {
+        return Err("The Jacobian at this state is not finite".to_string());
+    }
+    let eigenvalues = jacobian.complex_eigenvalues();
+    let scale = eigenvalues.iter().fold(1.0_f64, |acc, z| acc.max(z.norm()));
+    let zero = ZERO_TOLERANCE * scale;
+    
+    // The eigenvalues closest to zero, one per conservation law, are
+    // structural rather than dynamical.
+    let n_conserved = simulator.left_null_space().ncols();
+    let mut by_magnitude: Vec<usize> = (0..eigenvalues.len()).collect();
+    by_magnitude.sort_by(|&a, &b| eigenvalues[a].norm().total_cmp(&eigenvalues[b].norm()));
+    let conserved: Vec<usize> = by_magnitude.into_iter()
+        .take(n_conserved)
+        .filter(|&i| eigenvalues[i].norm() <= zero)
+        .collect();
+    
+    let mut modes: Vec<Mode> = eigenvalues.iter()
+        .enumerate()
+        .map(|(i, &lambda)| {
+            let vector = eigenvector(&jacobian, lambda);
+            Mode {
+                real: lambda.re,
+                imag: lambda.im,
+                timescale: if lambda.re.abs() > zero { 1.0 / lambda.re.abs() } else { f64::INFINITY },
+                period: if lambda.im.abs() > zero { Some(2.0 * std::f64::consts::PI / lambda.im.abs()) } else { None },
+                conserved: conserved.contains(&i),
+                dominant_species: dominant_species(model, &vector),
+            }
+        })
+        .collect();
+    modes.sort_by(|a, b| b.real.total_cmp(&a.real));
+    
+    let dynamic = modes.iter().filter(|m| !m.conserved);
+    let max_real = dynamic.clone().map(|m| m.real).fold(f64::NEG_INFINITY, f64::max);
+    let classification = if max_real > zero {
+        "unstable"
+    } else if max_real >= -zero {
+        "marginal"
+    } else {
+        "stable"
+    };
+    let oscillatory = dynamic.clone().any(|m| m.period.is_some());
+    
+    Ok(StabilityReport {
+        classification: classification.to_string(),
+        oscillatory,
+        modes,
+    })
+}
+
+/// Eigenvector for `lambda` by a few steps of inverse iteration on the
+/// slightly shifted complex matrix.
+fn eigenvector(jacobian: &DMatrix<f64>, lambda: Complex<f64>) -> DVector<Complex<f64>> {
+    let n = jacobian.nrows();
+    let shift = lambda + Complex::new(1e-10 * lambda.norm().max(1.0), 0.0);
+    let mut shifted = jacobian.map(|x| Complex::new(x, 0.0));
+    for i in 0..n {
+        shifted[(i, i)] -= shift;
+    }
+    let lu = shifted.lu();
+    
+    let mut vector = DVector::from_element(n, Complex::new(1.0, 0.0));
+    for _ in 0..3 {
+        match lu.solve(&vector) {
+            Some(next) if next.norm() > 0.0 && next.norm().is_finite() => {
+                vector = next.unscale(next.norm());
+            }
+            _ => break,
+        }
+    }
+    vector
+}
+
+fn dominant_species(model: &BioModelData, vector: &DVector<Complex<f64>>) -> Vec<(String, f64)> {
+    let total: f64 = vector.iter().map(|z| z.norm_sqr()).sum();
+    let mut weights: Vec<(usize, f64)> = vector.iter()
+        .map(|z| if total > 0.0 { z.norm_sqr() / total } else { 0.0 })
+        .enumerate()
+        .collect();
+    weights.sort_by(|a, b| b.1.total_cmp(&a.1));
+    
+    weights.into_iter()
+        .take(DOMINANT_SPECIES)
+        .map(|(i, w)| (model.species[i].id.clone(), w))
+        .collect()
+}
@@ -0,0 +1,367 @@
+use serde::{Serialize, Deserialize};
+
+use crate::simulator::SimulationResults;
+
+/// Fewest points a terminal-slope regression is fitted to.
+const MIN_TERMINAL_POINTS: usize = 3;
+
+/// Fits within this much adjusted R² of the best count as equally good; the
+/// one with more points then wins.
+const R_SQUARED_TOLERANCE: f64 = 1e-4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AucMethod {
+    /// Linear trapezoids throughout.
+    #[default]
+    Linear,
+    /// Log trapezoids wherever both ends are positive and unequal.
+    Log,
+    /// Linear trapezoids while concentrations rise or hold, log trapezoids
+    /// while they fall.
+    LinearUpLogDown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PkOptions {
+    pub auc_method: AucMethod,
+    /// Concentration for `time_above_threshold`; not computed when omitted.
+    pub threshold: Option<f64>,
+    /// Number of final points for the terminal slope. When omitted, the
+    /// count with the best adjusted R² is used, from 3 points up to all
+    /// points after Cmax.
+    pub terminal_points: Option<usize>,
+    /// Per-dose-interval metrics when the results carry a dosing schedule.
+    pub dose_intervals: bool,
+}
+
+impl Default for PkOptions {
+    fn default() -> Self {
+        PkOptions {
+            auc_method: AucMethod::Linear,
+            threshold: None,
+            terminal_points: None,
+            dose_intervals: true,
+        }
+    }
+}
+
+/// Non-compartmental metrics for one species, in the results' time and
+/// concentration units.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PkMetrics {
+    pub species_id: String,
+    pub cmax: f64,
+    pub tmax: f64,
+    /// Concentration and time of the last output point.
+    pub clast: f64,
+    pub tlast: f64,
+    /// AUC from the first to the last output time.
+    pub auc_last: f64,
+    /// `auc_last` extrapolated to infinity with the terminal slope.
+    pub auc_inf: Option<f64>,
+    /// Terminal elimination rate constant: minus the slope of ln(C) over
+    /// the final points.
+    pub lambda_z: Option<f64>,
+    pub half_life: Option<f64>,
+    pub terminal_points: Option<usize>,
+    pub r_squared_adjusted: Option<f64>,
+    /// AUMC over AUC, extrapolated to infinity when a terminal slope exists.
+    pub mean_residence_time: Option<f64>,
+    pub time_above_threshold: Option<f64>,
+    /// One entry per dose, from that dose to the next (the last runs to the
+    /// end of the results).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub intervals: Vec<DoseInterval>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoseInterval {
+    pub start: f64,
+    pub end: f64,
+    pub cmax: f64,
+    pub tmax: f64,
+    pub cmin: f64,
+    pub auc: f64,
+    pub time_above_threshold: Option<f64>,
+}
+
+/// Terminal-phase regression of ln(C) on time.
+struct TerminalFit {
+    lambda_z: f64,
+    points: usize,
+    r_squared_adjusted: f64,
+}
+
+/// PK summary of each of `species` (ids or names). Non-finite samples, such
+/// as missing cells of imported data, are skipped.
+pub fn pk_summary(results: &SimulationResults, species: &[String], options: &PkOptions) -> Result<Vec<PkMetrics>, String> {
+    species.iter()
+        .map(|s| species_metrics(results, s, options))
+        .collect()
+}
+
+fn species_metrics(results: &SimulationResults, species: &str, options: &PkOptions) -> Result<PkMetrics, String> {
+    let index = results.species_index(species)
+        .ok_or_else(|| format!("Species '{}' not found in the results", species))?;
+    let (times, values): (Vec<f64>, Vec<f64>) = results.time.iter()
+        .zip(results.get_species_trajectory(index))
+        .filter(|(t, c)| t.is_finite() && c.is_finite())
+        .map(|(&t, c)| (t, c))
+        .unzip();
+    if times.len() < 2 {
+        return Err(format!("Species '{}' has fewer than two samples", species));
+    }
+    if times.windows(2).any(|w| w[1] < w[0]) {
+        return Err("Result times must be non-decreasing".to_string());
+    }
+    
+    let peak = argmax(&values);
+    let n = times.len();
+    let (tlast, clast) = (times[n - 1], values[n - 1]);
+    let (auc_last, aumc_last) = areas(&times, &values, options.auc_method);
+    
+    let fit = terminal_fit(&times, &values, peak, options.terminal_points)?;
+    let lambda_z = fit.as_ref().map(|f| f.lambda_z);
+    let auc_inf = lambda_z.map(|l| auc_last + clast / l);
+    let mean_residence_time = match lambda_z {
+        Some(l) => {
+            let aumc_inf = aumc_last + clast * tlast / l + clast / (l * l);
+            aumc_inf / (auc_last + clast / l)
+        }
+        None => aumc_last / auc_last,
+    };
+    
+    let intervals = if options.dose_intervals {
+        dose_intervals(results, &times, &values, options)
+    } else {
+        Vec::new()
+    };
+    
+    Ok(PkMetrics {
+        species_id: results.species_ids.get(index).unwrap_or(&results.species_names[index]).clone(),
+        cmax: values[peak],
+        tmax: times[peak],
+        clast,
+        tlast,
+        auc_last,
+        auc_inf,
+        lambda_z,
+        half_life: lambda_z.map(|l| std::f64::consts::LN_2 / l),
+        terminal_points: fit.as_ref().map(|f| f.points),
+        r_squared_adjusted: fit.as_ref().map(|f| f.r_squared_adjusted),
+        mean_residence_time: Some(mean_residence_time).filter(|m| m.is_finite()),
+        time_above_threshold: options.threshold.map(|c| time_above(&times, &values, c)),
+        intervals,
+    })
+}
+
+/// Index of the first maximum.
+fn argmax(values: &[f64]) -> usize {
+    values.iter()
+        .enumerate()
+        .fold(0, |best, (i, &c)| if c > values[best] { i } else { best })
+}
+
+/// AUC and AUMC over the samples.
+fn areas(times: &[f64], values: &[f64], method: AucMethod) -> (f64, f64) {
+    let mut auc = 0.0;
+    let mut aumc = 0.0;
+    for k in 1..times.len() {
+        let (t0, t1, c0, c1) = (times[k - 1], times[k], values[k - 1], values[k]);
+        let dt = t1 - t0;
+        let log = match method {
+            AucMethod::Linear => false,
+            AucMethod::Log => true,
+            AucMethod::LinearUpLogDown => c1 < c0,
+        };
+        if log && c0 > 0.0 && c1 > 0.0 && c0 != c1 {
+            let ratio = (c0 / c1).ln();
+            auc += dt * (c0 - c1) / ratio;
+            aumc += dt * (c0 * t0 - c1 * t1) / ratio + dt * dt * (c0 - c1) / (ratio * ratio);
+        } else {
+            auc += dt * (c0 + c1) / 2.0;
+            aumc += dt * (c0 * t0 + c1 * t1) / 2.0;
+        }
+    }
+    (auc, aumc)
+}
+
+/// Least-squares fit of ln(C) on the last `points` samples after the peak,
+/// or the best-fitting count when `points` is `None`. No fit when fewer
+/// than three positive samples follow the peak or the slope is not
+/// negative.
+fn terminal_fit(times: &[f64], values: &[f64], peak: usize, points: Option<usize>) -> Result<Option<TerminalFit>, String> {
+    let n = times.len();
+    let available = n - peak - 1;
+    let counts = match points {
+        Some(p) if p < MIN_TERMINAL_POINTS => {
+            return Err(format!("terminalPoints must be at least {}", MIN_TERMINAL_POINTS));
+        }
+        Some(p) if p > available => return Ok(None),
+        Some(p) => p..=p,
+        None => MIN_TERMINAL_POINTS..=available,
+    };
+    
+    let mut best: Option<TerminalFit> = None;
+    for count in counts {
+        let (t, c) = (&times[n - count..], &values[n - count..]);
+        if c.iter().any(|&c| c <= 0.0) {
+            continue;
+        }
+        let ln_c: Vec<f64> = c.iter().map(|c| c.ln()).collect();
+        let Some((slope, r_squared)) = regression(t, &ln_c) else { continue };
+        if slope >= 0.0 {
+            continue;
+        }
+        let m = count as f64;
+        let r_squared_adjusted = 1.0 - (1.0 - r_squared) * (m - 1.0) / (m - 2.0);
+        let better = best.as_ref()
+            .is_none_or(|b| r_squared_adjusted >= b.r_squared_adjusted - R_SQUARED_TOLERANCE);
+        if better {
+            best = Some(TerminalFit { lambda_z: -slope, points: count, r_squared_adjusted });
+        }
+    }
+    Ok(best)
+}
+
+/// Slope and R² of the least-squares line through `(x, y)`.
+fn regression(x: &[f64], y: &[f64]) -> Option<(f64, f64)> {
+    let m = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / m;
+    let mean_y = y.iter().sum::<f64>() / m;
+    let sxx: f64 = x.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let syy: f64 = y.iter().map(|y| (y - mean_y).powi(2)).sum();
+    let sxy: f64 = x.iter().zip(y).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    if sxx <= 0.0 {
+        return None;
+    }
+    let r_squared = if syy > 0.0 { sxy * sxy / (sxx * syy) } else { 1.0 };
+    Some((sxy / sxx, r_squared))
+}
+
+/// Total time the linearly interpolated curve spends above `threshold`.
+fn time_above(times: &[f64], values: &[f64], threshold: f64) -> f64 {
+    let mut total = 0.0;
+    for k in 1..times.len() {
+        let (t0, t1, c0, c1) = (times[k - 1], times[k], values[k - 1], values[k]);
+        total += match (c0 > threshold, c1 > threshold) {
+            (true, true) => t1 - t0,
+            (false, false) => 0.0,
+            (true, false) => (t1 - t0) * (c0 - threshold) / (c0 - c1),
+            (false, true) => (t1 - t0) * (c1 - threshold) / (c1 - c0),
+        };
+    }
+    total
+}
+
+/// Metrics between consecutive dose times of every dosing schedule in the
+/// results.
+fn dose_intervals(results: &SimulationResults, times: &[f64], values: &[f64], options: &PkOptions) -> Vec<DoseInterval> {
+    let (first, last) = (times[0], times[times.len() - 1]);
+    let mut doses: Vec<f64> = results.dosing_schedules.iter()
+        .flat_map(|s| s.dose_times())
+        .filter(|&t| t >= first && t < last)
+        .collect();
+    doses.sort_by(f64::total_cmp);
+    doses.dedup();
+    
+    doses.iter()
+        .enumerate()
+        .filter_map(|(k, &start)| {
+            let next = doses.get(k + 1).copied();
+            let end = next.unwrap_or(last);
+            let (t, c) = window(times, values, start, end, next.is_some())?;
+            let peak = argmax(&c);
+            Some(DoseInterval {
+                start,
+                end,
+                cmax: c[peak],
+                tmax: t[peak],
+                cmin: c.iter().copied().fold(f64::INFINITY, f64::min),
+                auc: areas(&t, &c, options.auc_method).0,
+                time_above_threshold: options.threshold.map(|threshold| time_above(&t, &c, threshold)),
+            })
+        })
+        .collect()
+}
+
+/// Samples of one dose interval: from `start`, up to but excluding `end`
+/// when another dose follows, as a sample at a dose time already includes
+/// that dose. Interval ends without a sample of their own are extrapolated
+/// linearly from the two nearest samples inside the interval. `None` when
+/// fewer than two samples fall inside.
+fn window(times: &[f64], values: &[f64], start: f64, end: f64, dose_at_end: bool) -> Option<(Vec<f64>, Vec<f64>)> {
+    let (mut t, mut c): (Vec<f64>, Vec<f64>) = times.iter()
+        .zip(values)
+        .filter(|(&time, _)| time >= start && (time < end || (!dose_at_end && time <= end)))
+        .unzip();
+    let m = t.len();
+    if m < 2 {
+        return None;
+    }
+    
+    if t[m - 1] < end {
+        let value = crate::utils::interpolate(end, t[m - 2], t[m - 1], c[m - 2], c[m - 1]);
+        t.push(end);
+        c.push(value);
+    }
+    if t[0] > start {
+        let value = crate::utils::interpolate(start, t[0], t[1], c[0], c[1]);
+        t.insert(0, start);
+        c.insert(0, value);
+    }
+    Some((t, c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const C0: f64 = 10.0;
+    const K: f64 = 0.3;
+    
+    /// C(t) = C0·e^(-Kt) sampled every half hour for a day.
+    fn exponential() -> SimulationResults {
+        let time: Vec<f64> = (0..=48).map(|k| k as f64 * 0.5).collect();
+        SimulationResults {
+            values: time.iter().map(|t| C0 * (-K * t).exp()).collect(),
+            time,
+            species_ids: vec!["C".to_string()],
+            species_names: vec!["C".to_string()],
+            num_species: 1,
+            ..Default::default()
+        }
+    }
+    
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() <= 1e-9 * expected.abs(), "{} vs {}", actual, expected);
+    }
+    
+    #[test]
+    fn metrics_of_an_exponential_decline() {
+        let options = PkOptions { auc_method: AucMethod::Log, threshold: Some(5.0), ..Default::default() };
+        let metrics = &pk_summary(&exponential(), &["C".to_string()], &options).unwrap()[0];
+        
+        assert_eq!((metrics.cmax, metrics.tmax), (C0, 0.0));
+        assert_close(metrics.lambda_z.unwrap(), K);
+        assert_close(metrics.half_life.unwrap(), std::f64::consts::LN_2 / K);
+        assert_eq!(metrics.terminal_points, Some(48));
+        assert_close(metrics.auc_last, C0 / K * (1.0 - (-K * 24.0).exp()));
+        // Log trapezoids are exact for an exponential, so the extrapolated
+        // areas are the analytic ones.
+        assert_close(metrics.auc_inf.unwrap(), C0 / K);
+        assert_close(metrics.mean_residence_time.unwrap(), 1.0 / K);
+        // Linear interpolation between samples slightly overstates it.
+        let above = metrics.time_above_threshold.unwrap();
+        assert!(above >= 2.0_f64.ln() / K && above < 2.0_f64.ln() / K + 0.01);
+    }
+    
+    #[test]
+    fn linear_trapezoids_overestimate_a_convex_decline() {
+        let metrics = &pk_summary(&exponential(), &["C".to_string()], &PkOptions::default()).unwrap()[0];
+        let exact = C0 / K * (1.0 - (-K * 24.0).exp());
+        assert!(metrics.auc_last > exact && metrics.auc_last < exact * 1.01);
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt::Write;
+
+use crate::simulator::SimulationResults;
+use crate::spec::ChartSpec;
+
+const DEFAULT_WIDTH: f64 = 800.0;
+const DEFAULT_HEIGHT: f64 = 500.0;
+
+const MARGIN_LEFT: f64 = 80.0;
+const MARGIN_RIGHT: f64 = 30.0;
+const MARGIN_TOP: f64 = 50.0;
+const MARGIN_BOTTOM: f64 = 60.0;
+
+/// Approximate number of ticks on a linear axis.
+const TARGET_TICKS: f64 = 6.0;
+
+/// Tableau 10.
+const PALETTE: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd",
+    "#8c564b", "#e377c2", "#7f7f7f", "#bcbd22", "#17becf",
+];
+
+/// A data range mapped onto a pixel range.
+struct Axis {
+    min: f64,
+    max: f64,
+    log: bool,
+    ticks: Vec<f64>,
+    /// Decimal places for tick labels on linear axes.
+    decimals: usize,
+}
+
+impl Axis {
+    fn new(values: impl Iterator<Item = f64>, log: bool) -> Result<Axis, String> {
+        let (lo, hi) = values
+            .filter(|v| v.is_finite() && (!log || *v > 0.0))
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+        if lo > hi {
+            return Err(if log {
+                "No positive values to plot on a log scale".to_string()
+            } else {
+                "No finite values to plot".to_string()
+            });
+        }
+        
+        if log {
+            let min_decade = lo.log10().floor();
+            let max_decade = hi.log10().ceil().max(min_decade + 1.0);
+            let ticks = (min_decade as i32..=max_decade as i32)
+                .map(|k| 10f64.powi(k))
+                .collect();
+            return Ok(Axis {
+                min: 10f64.powf(min_decade),
+                max: 10f64.powf(max_decade),
+                log,
+                ticks,
+                decimals: 0,
+            });
+        }
+        
+        let (lo, hi) = if lo == hi {
+            let pad = if lo == 0.0 { 1.0 } else { lo.abs() * 0.1 };
+            (lo - pad, hi + pad)
+        } else {
+            (lo, hi)
+        };
+        let step = nice_step((hi - lo) / TARGET_TICKS);
+        let min = (lo / step).floor() * step;
+        let max = (hi / step).ceil() * step;
+        let count = ((max - min) / step).round() as usize;
+        Ok(Axis {
+            min,
+            max,
+            log,
+            // Snap float noise around zero so the tick reads "0".
+            ticks: (0..=count)
+                .map(|k| min + k as f64 * step)
+                .map(|v| if v.abs() < step * 1e-9 { 0.0 } else { v })
+                .collect(),
+            decimals: (-step.log10().floor()).max(0.0) as usize,
+        })
+    }
+    
+    /// Position of `v` as a fraction of the axis length.
+    fn fraction(&self, v: f64) -> f64 {
+        if self.log {
+            (v.log10() - self.min.log10()) / (self.max.log10() - self.min.log10())
+        } else {
+            (v - self.min) / (self.max - self.min)
+        }
+    }
+    
+    fn label(&self, v: f64) -> String {
+        let magnitude = v.abs();
+        if v != 0.0 && !(1e-3..1e5).contains(&magnitude) {
+            format!("{:e}", v)
+        } else if self.log {
+            format!("{}", v)
+        } else {
+            format!("{:.*}", self.decimals, v)
+        }
+    }
+}
+
+/// Smallest of 1, 2 or 5 × 10^k that is at least `raw`.
+fn nice_step(raw: f64) -> f64 {
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let normalized = raw / magnitude;
+    let nice = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders a line chart of `results` as a standalone SVG document.
+///
+/// The x axis is `axisParamX`: "time" (default), "gestational_week", or a
+/// species or recorded variable. Each series is a species or recorded
+/// variable, matched by id and then by name.
+/// Points that cannot be drawn (non-finite, or non-positive on a log axis)
+/// break the line.
+pub fn render_svg(results: &SimulationResults, chart: &ChartSpec) -> Result<String, String> {
+    if let Some(kind) = chart.kind.as_deref() {
+        if kind != "line" {
+            return Err(format!("Unsupported chart type '{}' (only 'line' is available)", kind));
+        }
+    }
+    if chart.series.is_empty() {
+        return Err("Chart has no series".to_string());
+    }
+    
+    let x_param = chart.axis_param_x.as_deref().unwrap_or("time");
+    let xs: Vec<f64> = match x_param {
+        "time" => results.time.clone(),
+        "gestational_week" => results.gestational_week.clone()
+            .or_else(|| results.trajectory(x_param))
+            .ok_or_else(|| "Results have no gestational_week axis; set gestationalStartWeek".to_string())?,
+        id => results.trajectory(id)
+            .ok_or_else(|| format!("X axis '{}' is not time, gestational_week, a species or a recorded variable", id))?,
+    };
+    let series: Vec<(String, String, Vec<f64>)> = chart.series.iter()
+        .enumerate()
+        .map(|(k, s)| {
+            let ys = results.trajectory(&s.param)
+                .ok_or_else(|| format!("Series '{}' is not a species or recorded variable in the results", s.param))?;
+            let label = s.label.clone().unwrap_or_else(|| s.param.clone());
+            let color = s.color.clone().unwrap_or_else(|| PALETTE[k % PALETTE.len()].to_string());
+            Ok((label, color, ys))
+        })
+        .collect::<Result<_, String>>()?;
+    
+    let x_axis = Axis::new(xs.iter().copied(), chart.log_scale_x)?;
+    let y_axis = Axis::new(series.iter().flat_map(|(_, _, ys)| ys.iter().copied()), chart.log_scale_y)?;
+    
+    let width = chart.width.unwrap_or(DEFAULT_WIDTH);
+    let height = chart.height.unwrap_or(DEFAULT_HEIGHT);
+    if !(width.is_finite() && width > MARGIN_LEFT + MARGIN_RIGHT) {
+        return Err(format!("Chart width {} leaves no room inside the {} px of margins", width, MARGIN_LEFT + MARGIN_RIGHT));
+    }
+    if !(height.is_finite() && height > MARGIN_TOP + MARGIN_BOTTOM) {
+        return Err(format!("Chart height {} leaves no room inside the {} px of margins", height, MARGIN_TOP + MARGIN_BOTTOM));
+    }
+    let plot_width = width - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = height - MARGIN_TOP - MARGIN_BOTTOM;
+    let px = |x: f64| MARGIN_LEFT + x_axis.fraction(x) * plot_width;
+    let py = |y: f64| MARGIN_TOP + (1.0 - y_axis.fraction(y)) * plot_height;
+    let bottom = MARGIN_TOP + plot_height;
+    let right = MARGIN_LEFT + plot_width;
+    
+    // Writing to a String cannot fail.
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#, w = width, h = height);
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+    if let Some(title) = &chart.title {
+        let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="middle" font-size="16" font-weight="bold">{}</text>"#, width / 2.0, MARGIN_TOP / 2.0 + 6.0, escape(title));
+    }
+    
+    for &tick in &x_axis.ticks {
+        let x = px(tick);
+        let _ = writeln!(svg, r##"<line x1="{x:.2}" y1="{MARGIN_TOP}" x2="{x:.2}" y2="{bottom}" stroke="#e5e5e5"/>"##);
+        let _ = writeln!(svg, r##"<line x1="{x:.2}" y1="{bottom}" x2="{x:.2}" y2="{:.2}" stroke="#333"/>"##, bottom + 5.0);
+        let _ = writeln!(svg, r#"<text x="{x:.2}" y="{:.2}" text-anchor="middle">{}</text>"#, bottom + 18.0, x_axis.label(tick));
+    }
+    for &tick in &y_axis.ticks {
+        let y = py(tick);
+        let _ = writeln!(svg, r##"<line x1="{MARGIN_LEFT}" y1="{y:.2}" x2="{right}" y2="{y:.2}" stroke="#e5e5e5"/>"##);
+        let _ = writeln!(svg, r##"<line x1="{:.2}" y1="{y:.2}" x2="{MARGIN_LEFT}" y2="{y:.2}" stroke="#333"/>"##, MARGIN_LEFT - 5.0);
+        let _ = writeln!(svg, r#"<text x="{:.2}" y="{:.2}" text-anchor="end">{}</text>"#, MARGIN_LEFT - 8.0, y + 4.0, y_axis.label(tick));
+    }
+    let _ = writeln!(svg, r##"<path d="M{MARGIN_LEFT},{MARGIN_TOP}V{bottom}H{right}" fill="none" stroke="#333"/>"##);
+    
+    if let Some(label) = &chart.axis_label_x {
+        let _ = writeln!(svg, r#"<text x="{:.2}" y="{:.2}" text-anchor="middle">{}</text>"#, MARGIN_LEFT + plot_width / 2.0, height - 15.0, escape(label));
+    }
+    if let Some(label) = &chart.axis_label_y {
+        let (x, y) = (20.0, MARGIN_TOP + plot_height / 2.0);
+        let _ = writeln!(svg, r#"<text x="{x}" y="{y:.2}" text-anchor="middle" transform="rotate(-90 {x} {y:.2})">{}</text>"#, escape(label));
+    }
+    
+    for (_, color, ys) in &series {
+        let mut segment = String::new();
+        for (&x, &y) in xs.iter().zip(ys) {
+            let drawable = x.is_finite() && y.is_finite()
+                && (!x_axis.log || x > 0.0)
+                && (!y_axis.log || y > 0.0);
+            if drawable {
+                let _ = write!(segment, "{:.2},{:.2} ", px(x), py(y));
+            } else if !segment.is_empty() {
+                let _ = writeln!(svg, r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#, segment.trim_end(), escape(color));
+                segment.clear();
+            }
+        }
+        if !segment.is_empty() {
+            let _ = writeln!(svg, r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#, segment.trim_end(), escape(color));
+        }
+    }
+    
+    let legend_width = series.iter()
+        .map(|(label, _, _)| label.chars().count() as f64 * 7.0)
+        .fold(0.0, f64::max) + 40.0;
+    let legend_x = right - legend_width - 10.0;
+    let legend_y = MARGIN_TOP + 10.0;
+    let _ = writeln!(svg, r##"<rect x="{legend_x:.2}" y="{legend_y:.2}" width="{legend_width:.2}" height="{:.2}" fill="white" fill-opacity="0.85" stroke="#ccc"/>"##, series.len() as f64 * 18.0 + 8.0);
+    for (k, (label, color, _)) in series.iter().enumerate() {
+        let y = legend_y + 16.0 + k as f64 * 18.0;
+        let _ = writeln!(svg, r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="{}" stroke-width="2"/>"#, legend_x + 8.0, y - 4.0, legend_x + 28.0, y - 4.0, escape(color));
+        let _ = writeln!(svg, r#"<text x="{:.2}" y="{y:.2}">{}</text>"#, legend_x + 34.0, escape(label));
+    }
+    
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+/// `filename` with its extension replaced by `.svg`, for rendering the
+/// figures a config names as PNGs.
+pub fn svg_filename(filename: &str) -> String {
+    match filename.rsplit_once('.') {
+        Some((stem, _)) => format!("{}.svg", stem),
+        None => format!("{}.svg", filename),
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use serde::Serialize;
+
+use crate::simulator::{ParameterSchedule, RunInfo, SimulationResults};
+use crate::timeline::TimeUnit;
+
+/// Name of the column tagging each row with the run it came from.
+pub const RUN_ID_COLUMN: &str = "run_id";
+
+/// One entry of the `runs` schema metadata.
+#[derive(Serialize)]
+struct RunMetadata<'a> {
+    #[serde(rename = "runId")]
+    run_id: &'a str,
+    #[serde(flatten)]
+    info: &'a RunInfo,
+    #[serde(rename = "parameterSchedules", skip_serializing_if = "<[_]>::is_empty")]
+    parameter_schedules: &'a [ParameterSchedule],
+    #[serde(rename = "timeUnit", skip_serializing_if = "Option::is_none")]
+    time_unit: Option<TimeUnit>,
+}
+
+struct Run {
+    id: String,
+    info: RunInfo,
+    parameter_schedules: Vec<ParameterSchedule>,
+    time_unit: Option<TimeUnit>,
+    batch: RecordBatch,
+}
+
+/// Runs over the same species, exported together: one record batch (or
+/// Parquet row group) per run, tagged by a `run_id` column.
+pub struct ArrowEnsemble {
+    model_id: Option<String>,
+    species: Vec<String>,
+    runs: Vec<Run>,
+}
+
+impl ArrowEnsemble {
+    pub fn new(model_id: Option<String>) -> Self {
+        ArrowEnsemble {
+            model_id,
+            species: Vec::new(),
+            runs: Vec::new(),
+        }
+    }
+    
+    pub fn num_runs(&self) -> usize {
+        self.runs.len()
+    }
+    
+    /// Appends a run. Every run must have the same species columns, in the
+    /// same order, as the first. Without `info`, the run info the simulator
+    /// recorded in `results` is used.
+    pub fn add_run(&mut self, run_id: &str, results: &SimulationResults, info: Option<RunInfo>) -> Result<(), String> {
+        if self.runs.iter().any(|run| run.id == run_id) {
+            return Err(format!("Run '{}' was already added", run_id));
+        }
+        if results.values.len() != results.time.len() * results.num_species {
+            return Err("Results values do not match time points times species".to_string());
+        }
+        let species = species_columns(results);
+        if self.runs.is_empty() {
+            if let Some(duplicate) = species.iter().enumerate().find(|(i, s)| species[..*i].contains(s)) {
+                return Err(format!("Species column '{}' appears twice", duplicate.1));
+            }
+            if species.iter().any(|s| s == RUN_ID_COLUMN || s == "time") {
+                return Err(format!("Species columns cannot be named '{}' or 'time'", RUN_ID_COLUMN));
+            }
+            self.species = species;
+        } else if species != self.species {
+            return Err(format!("Run '{}' has different species from the ensemble's first run", run_id));
+        }
+        
+        let n = results.time.len();
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(vec![run_id; n])),
+            Arc::new(Float64Array::from(results.time.clone())),
+        ];
+        for i in 0..results.num_species {
+            columns.push(Arc::new(Float64Array::from(results.get_species_trajectory(i))));
+        }
+        let batch = RecordBatch::try_new(self.column_schema(), columns)
+            .map_err(|e| format!("Failed to build record batch: {}", e))?;
+        
+        self.runs.push(Run {
+            id: run_id.to_string(),
+            info: info.or_else(|| results.run_info.clone()).unwrap_or_default(),
+            parameter_schedules: results.parameter_schedules.clone(),
+            time_unit: results.time_unit,
+            batch,
+        });
+        Ok(())
+    }
+    
+    /// The Arrow IPC stream: the schema, then one record batch per run.
+    pub fn to_ipc(&self) -> Result<Vec<u8>, String> {
+        let schema = self.schema()?;
+        let mut writer = StreamWriter::try_new(Vec::new(), &schema)
+            .map_err(|e| format!("Failed to start Arrow stream: {}", e))?;
+        for run in &self.runs {
+            let batch = run.batch.clone().with_schema(schema.clone())
+                .map_err(|e| format!("Failed to write run '{}': {}", run.id, e))?;
+            writer.write(&batch)
+                .map_err(|e| format!("Failed to write run '{}': {}", run.id, e))?;
+        }
+        writer.into_inner()
+            .map_err(|e| format!("Failed to finish Arrow stream: {}", e))
+    }
+    
+    /// A Parquet file with one row group per run. Native builds only.
+    #[cfg(feature = "parquet")]
+    pub fn to_parquet(&self) -> Result<Vec<u8>, String> {
+        let schema = self.schema()?;
+        let mut writer = parquet::arrow::ArrowWriter::try_new(Vec::new(), schema.clone(), None)
+            .map_err(|e| format!("Failed to start Parquet file: {}", e))?;
+        for run in &self.runs {
+            let batch = run.batch.clone().with_schema(schema.clone())
+                .map_err(|e| format!("Failed to write run '{}': {}", run.id, e))?;
+            writer.write(&batch)
+                .map_err(|e| format!("Failed to write run '{}': {}", run.id, e))?;
+            // Closes the row group so each run gets its own
+            writer.flush()
+                .map_err(|e| format!("Failed to write run '{}': {}", run.id, e))?;
+        }
+        writer.into_inner()
+            .map_err(|e| format!("Failed to finish Parquet file: {}", e))
+    }
+    
+    fn column_schema(&self) -> SchemaRef {
+        let mut fields = vec![
+            Field::new(RUN_ID_COLUMN, DataType::Utf8, false),
+            Field::new("time", DataType::Float64, false),
+        ];
+        fields.extend(self.species.iter().map(|s| Field::new(s, DataType::Float64, true)));
+        Arc::new(Schema::new(fields))
+    }
+    
+    /// Column schema plus metadata: `model_id` when known, and `runs`, a
+    /// JSON array with each run's solver settings, parameter overrides and
+    /// schedules.
+    fn schema(&self) -> Result<SchemaRef, String> {
+        if self.runs.is_empty() {
+            return Err("No runs to export".to_string());
+        }
+        let runs: Vec<RunMetadata> = self.runs.iter()
+            .map(|run| RunMetadata {
+                run_id: &run.id,
+                info: &run.info,
+                parameter_schedules: &run.parameter_schedules,
+                time_unit: run.time_unit,
+            })
+            .collect();
+        let mut metadata = HashMap::new();
+        if let Some(model_id) = &self.model_id {
+            metadata.insert("model_id".to_string(), model_id.clone());
+        }
+        metadata.insert("runs".to_string(), serde_json::to_string(&runs)
+            .map_err(|e| format!("Failed to encode run metadata: {}", e))?);
+        
+        let schema = self.column_schema().as_ref().clone().with_metadata(metadata);
+        Ok(Arc::new(schema))
+    }
+}
+
+/// Species column names: ids, or names for results that carry none.
+fn species_columns(results: &SimulationResults) -> Vec<String> {
+    if results.species_ids.len() == results.num_species {
+        results.species_ids.clone()
+    } else {
+        results.species_names.clone()
+    }
+}
+
+/// A single run as an Arrow IPC stream, with run id "0".
+pub fn to_arrow_ipc(results: &SimulationResults, model_id: Option<String>, info: Option<RunInfo>) -> Result<Vec<u8>, String> {
+    let mut ensemble = ArrowEnsemble::new(model_id);
+    ensemble.add_run("0", results, info)?;
+    ensemble.to_ipc()
+}
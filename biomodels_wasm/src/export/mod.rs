@@ -0,0 +1,279 @@
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+use crate::simulator::SimulationResults;
+use crate::utils;
+
+pub mod arrow;
+
+pub use arrow::ArrowEnsemble;
+pub use crate::simulator::RunInfo;
+
+/// Header text of the gestational-week column.
+const GESTATIONAL_WEEK_COLUMN: &str = "gestational_week";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnHeader {
+    #[default]
+    Id,
+    Name,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CsvOptions {
+    /// Field separator, e.g. "," (default) or "\t" for TSV. Fields holding
+    /// it are quoted.
+    #[serde(default = "default_delimiter")]
+    pub delimiter: String,
+    /// Columns to include, by id or name, in this order: species, or
+    /// variables such as observables, compartments and parameters. All
+    /// species when omitted.
+    #[serde(default)]
+    pub columns: Option<Vec<String>>,
+    /// Whether value columns are headed by id or by name.
+    #[serde(default)]
+    pub header: ColumnHeader,
+    #[serde(default = "default_time_header", rename = "timeHeader")]
+    pub time_header: String,
+    /// Adds a `gestational_week` column after time, when the results have
+    /// one.
+    #[serde(default, rename = "gestationalWeek")]
+    pub gestational_week: bool,
+    /// Fixed number of decimals; shortest round-trip form when omitted.
+    #[serde(default)]
+    pub precision: Option<usize>,
+    #[serde(default)]
+    pub scientific: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: default_delimiter(),
+            columns: None,
+            header: ColumnHeader::Id,
+            time_header: default_time_header(),
+            gestational_week: false,
+            precision: None,
+            scientific: false,
+        }
+    }
+}
+
+fn default_delimiter() -> String {
+    ",".to_string()
+}
+
+fn default_time_header() -> String {
+    "time".to_string()
+}
+
+impl CsvOptions {
+    fn format(&self, value: f64) -> String {
+        match (self.precision, self.scientific) {
+            (Some(p), true) => format!("{:.*e}", p, value),
+            (Some(p), false) => format!("{:.*}", p, value),
+            (None, true) => format!("{:e}", value),
+            (None, false) => format!("{}", value),
+        }
+    }
+}
+
+/// Writes results as delimited text: a header row, then one row per time
+/// point with time first.
+pub fn to_csv(results: &SimulationResults, options: &CsvOptions) -> Result<String, String> {
+    let delimiter = options.delimiter.as_str();
+    if delimiter.chars().count() != 1 || delimiter.contains(['"', '\n', '\r']) {
+        return Err(format!("Delimiter must be a single character other than a quote or line break, got {:?}", delimiter));
+    }
+    if results.values.len() != results.time.len() * results.num_species {
+        return Err(format!(
+            "Results have {} values for {} time points and {} species",
+            results.values.len(), results.time.len(), results.num_species
+        ));
+    }
+    let columns: Vec<Column> = match &options.columns {
+        Some(columns) => columns.iter()
+            .map(|c| resolve_column(results, c))
+            .collect::<Result<_, _>>()?,
+        None => (0..results.num_species).map(Column::Species).collect(),
+    };
+    let weeks = match (options.gestational_week, &results.gestational_week) {
+        (true, Some(weeks)) if weeks.len() == results.time.len() => Some(weeks),
+        (true, Some(weeks)) => return Err(format!(
+            "Results have {} gestational weeks for {} time points",
+            weeks.len(), results.time.len()
+        )),
+        (true, None) => return Err("Results have no gestational_week axis; set gestationalStartWeek".to_string()),
+        (false, _) => None,
+    };
+    
+    let mut header = vec![options.time_header.clone()];
+    if weeks.is_some() {
+        header.push(GESTATIONAL_WEEK_COLUMN.to_string());
+    }
+    for &column in &columns {
+        let name = match column {
+            Column::Species(i) => {
+                let names = match options.header {
+                    ColumnHeader::Id if !results.species_ids.is_empty() => &results.species_ids,
+                    _ => &results.species_names,
+                };
+                names.get(i)
+                    .ok_or_else(|| format!("Results have no header for species column {}", i))?
+            }
+            Column::Variable(v) => match options.header {
+                ColumnHeader::Id => &results.variables[v].id,
+                ColumnHeader::Name => &results.variables[v].name,
+            },
+        };
+        header.push(name.clone());
+    }
+    
+    // Writing to a String cannot fail.
+    let mut out = header.iter()
+        .map(|name| utils::quote_field(name, delimiter))
+        .collect::<Vec<_>>()
+        .join(delimiter);
+    out.push('\n');
+    let cell = |value: f64| utils::quote_field(&options.format(value), delimiter);
+    for (row, &t) in results.time.iter().enumerate() {
+        out.push_str(&cell(t));
+        if let Some(weeks) = weeks {
+            let _ = write!(out, "{}{}", delimiter, cell(weeks[row]));
+        }
+        for &column in &columns {
+            let value = match column {
+                Column::Species(i) => results.values[row * results.num_species + i],
+                Column::Variable(v) => results.variables[v].values[row],
+            };
+            let _ = write!(out, "{}{}", delimiter, cell(value));
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Column {
+    Species(usize),
+    Variable(usize),
+}
+
+/// The species, or failing that the variable, with id or name `name`.
+fn resolve_column(results: &SimulationResults, name: &str) -> Result<Column, String> {
+    if let Some(i) = results.species_index(name) {
+        return Ok(Column::Species(i));
+    }
+    let v = results.variables.iter()
+        .position(|v| v.id == name)
+        .or_else(|| results.variables.iter().position(|v| v.name == name))
+        .ok_or_else(|| format!("Column '{}' is not a species or variable in the results", name))?;
+    if results.variables[v].values.len() != results.time.len() {
+        return Err(format!(
+            "Variable '{}' has {} values for {} time points",
+            name, results.variables[v].values.len(), results.time.len()
+        ));
+    }
+    Ok(Column::Variable(v))
+}
+
+/// Reads delimited text back into results. Times come from `time_column`
+/// (the first column by default), a `gestational_week` column becomes the
+/// gestational-week axis, and every other column is a species named by
+/// its header. `delimiter` is detected from the header when not given.
+/// Empty cells and `NA` read as NaN.
+pub fn from_csv(text: &str, time_column: Option<&str>, delimiter: Option<char>) -> Result<SimulationResults, String> {
+    let (columns, rows) = utils::parse_table(text, delimiter)?;
+    let time_index = match time_column {
+        Some(name) => columns.iter()
+            .position(|c| c == name)
+            .ok_or_else(|| format!("Time column '{}' not found", name))?,
+        None => 0,
+    };
+    let week_index = columns.iter()
+        .position(|c| c == GESTATIONAL_WEEK_COLUMN)
+        .filter(|&i| i != time_index);
+    let species: Vec<usize> = (0..columns.len())
+        .filter(|&i| i != time_index && Some(i) != week_index)
+        .collect();
+    
+    let time: Vec<f64> = rows.iter().map(|row| row[time_index]).collect();
+    if time.iter().any(|t| !t.is_finite()) {
+        return Err("Time column contains missing or non-finite values".to_string());
+    }
+    
+    let names: Vec<String> = species.iter().map(|&i| columns[i].clone()).collect();
+    Ok(SimulationResults {
+        values: rows.iter()
+            .flat_map(|row| species.iter().map(move |&i| row[i]))
+            .collect(),
+        num_species: species.len(),
+        species_ids: names.clone(),
+        species_names: names,
+        gestational_week: week_index.map(|w| rows.iter().map(|row| row[w]).collect()),
+        time,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn results() -> SimulationResults {
+        SimulationResults {
+            time: vec![0.0, 0.1, 0.30000000000000004],
+            values: vec![1.0, -2.5e-300, 0.1 + 0.2, f64::MAX, 1.0 / 3.0, 0.0],
+            species_ids: vec!["A,B".to_string(), "say \"hi\"".to_string()],
+            species_names: vec!["A,B".to_string(), "say \"hi\"".to_string()],
+            num_species: 2,
+            gestational_week: Some(vec![12.0, 12.5, 13.0]),
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn csv_round_trips_exactly() {
+        for delimiter in [",", "\t", ";"] {
+            let options = CsvOptions {
+                delimiter: delimiter.to_string(),
+                gestational_week: true,
+                ..Default::default()
+            };
+            let written = to_csv(&results(), &options).unwrap();
+            let read = from_csv(&written, None, None).unwrap();
+            
+            let original = results();
+            assert_eq!(read.species_ids, original.species_ids);
+            assert_eq!(read.time, original.time);
+            assert_eq!(read.values, original.values);
+            assert_eq!(read.gestational_week, original.gestational_week);
+        }
+    }
+    
+    #[test]
+    fn csv_quotes_fields_holding_the_delimiter() {
+        let written = to_csv(&results(), &CsvOptions::default()).unwrap();
+        assert!(written.starts_with("time,\"A,B\",\"say \"\"hi\"\"\"\n"));
+    }
+    
+    #[test]
+    fn csv_columns_include_observables() {
+        let model = crate::models::testing::mass_action(&[("A", 2.0), ("B", 1.0)], &[("A", "B", 0.5)]);
+        let mut sim = crate::simulator::Simulator::new(&model);
+        sim.set_observables(vec![crate::simulator::Observable { id: "total".to_string(), expression: "A + B".to_string() }]).unwrap();
+        let results = sim.simulate(1.0, 0.5, "rk4").unwrap();
+        
+        let options = CsvOptions { columns: Some(vec!["total".to_string(), "A".to_string()]), ..Default::default() };
+        let csv = to_csv(&results, &options).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("time,total,A"));
+        assert_eq!(lines.next(), Some("0,3,2"));
+        
+        let options = CsvOptions { columns: Some(vec!["missing".to_string()]), ..Default::default() };
+        assert!(to_csv(&results, &options).is_err());
+    }
+}
@@ -1,10 +1,17 @@
 use wasm_bindgen::prelude::*;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 pub mod parser;
 pub mod simulator;
 pub mod models;
 pub mod utils;
+pub mod analysis;
+pub mod math;
+pub mod timeline;
+pub mod spec;
+pub mod chart;
+pub mod export;
 
 use models::BioModelData;
 use simulator::Simulator;
@@ -22,6 +29,8 @@ macro_rules! console_log {
 #[wasm_bindgen]
 pub struct BioModel {
     model_data: BioModelData,
+    /// Parameter values as parsed, for reporting overrides.
+    parsed_parameters: Vec<models::Parameter>,
     simulator: Simulator,
 }
 
@@ -41,6 +50,7 @@ impl BioModel {
                 let simulator = Simulator::new(&model_data);
                 
                 Ok(BioModel {
+                    parsed_parameters: model_data.parameters.clone(),
                     model_data,
                     simulator,
                 })
@@ -54,27 +64,234 @@ impl BioModel {
         let config: SimulationConfig = serde_wasm_bindgen::from_value(config)
             .map_err(|e| JsValue::from_str(&format!("Invalid config: {}", e)))?;
         
-        console_log!("Running simulation from t=0 to t={} with step {}", 
-            config.time_end, config.time_step);
+        let results = self.run(&config)?;
         
-        let num_steps = (config.time_end / config.time_step) as usize;
-        console_log!("This will generate {} time points", num_steps + 1);
+        serde_wasm_bindgen::to_value(&results)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize results: {}", e)))
+    }
+    
+    /// Like `simulate`, but keeps the results in WASM memory behind a handle
+    /// instead of converting them to nested JS arrays.
+    #[wasm_bindgen(js_name = simulateHandle)]
+    pub fn simulate_handle(&mut self, config: JsValue) -> Result<SimulationHandle, JsValue> {
+        let config: SimulationConfig = serde_wasm_bindgen::from_value(config)
+            .map_err(|e| JsValue::from_str(&format!("Invalid config: {}", e)))?;
         
-        // Warn if too many points
-        if num_steps > 10000 {
-            console_log!("Warning: Large simulation with {} steps may be slow", num_steps);
-        }
+        Ok(SimulationHandle::new(self.run(&config)?))
+    }
+    
+    /// Continues from the current simulator state (e.g. after `loadState`)
+    /// up to `config.timeEnd` instead of restarting at t=0. Run options stay
+    /// as the last run or the loaded checkpoint left them; only the time
+    /// range, method and time-axis options of `config` are used.
+    #[wasm_bindgen(js_name = continueSimulation)]
+    pub fn continue_simulation(&mut self, config: JsValue) -> Result<JsValue, JsValue> {
+        let config: SimulationConfig = serde_wasm_bindgen::from_value(config)
+            .map_err(|e| JsValue::from_str(&format!("Invalid config: {}", e)))?;
         
-        let results = self.simulator.simulate(
+        let mut results = self.simulator.advance(
             config.time_end,
             config.time_step,
             &config.method
         )?;
+        config.annotate(&mut results);
+        self.record_overrides(&mut results);
         
         serde_wasm_bindgen::to_value(&results)
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize results: {}", e)))
     }
     
+    #[wasm_bindgen(js_name = saveState)]
+    pub fn save_state(&self) -> Result<String, JsValue> {
+        self.simulator.save_state().to_json()
+    }
+    
+    #[wasm_bindgen(js_name = loadState)]
+    pub fn load_state(&mut self, blob: &str) -> Result<(), JsValue> {
+        let saved = simulator::SimulatorState::from_json(blob)?;
+        self.simulator.load_state(&saved)?;
+        
+        for param in &mut self.model_data.parameters {
+            if let Some(value) = saved.parameters.get(&param.id) {
+                param.value = *value;
+            }
+        }
+        Ok(())
+    }
+    
+    #[wasm_bindgen(js_name = steadyState)]
+    pub fn steady_state(&self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: simulator::SteadyStateOptions = if options.is_undefined() || options.is_null() {
+            simulator::SteadyStateOptions::default()
+        } else {
+            serde_wasm_bindgen::from_value(options)
+                .map_err(|e| JsValue::from_str(&format!("Invalid steady-state options: {}", e)))?
+        };
+        
+        let result = self.simulator.steady_state(&options)
+            .map_err(|e| JsValue::from_str(&e))?;
+        
+        serde_wasm_bindgen::to_value(&result)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize steady state: {}", e)))
+    }
+    
+    #[wasm_bindgen(js_name = stability)]
+    pub fn stability(&self, state: Vec<f64>) -> Result<JsValue, JsValue> {
+        let report = analysis::stability(&self.simulator, &self.model_data, &state)
+            .map_err(|e| JsValue::from_str(&e))?;
+        
+        serde_wasm_bindgen::to_value(&report)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize stability report: {}", e)))
+    }
+    
+    #[wasm_bindgen(js_name = continuation)]
+    pub fn continuation(&self, param_id: &str, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: analysis::ContinuationOptions = if options.is_undefined() || options.is_null() {
+            analysis::ContinuationOptions::default()
+        } else {
+            serde_wasm_bindgen::from_value(options)
+                .map_err(|e| JsValue::from_str(&format!("Invalid continuation options: {}", e)))?
+        };
+        
+        let result = analysis::continuation(&self.simulator, &self.model_data, param_id, &options)
+            .map_err(|e| JsValue::from_str(&e))?;
+        
+        serde_wasm_bindgen::to_value(&result)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize continuation: {}", e)))
+    }
+    
+    /// Adds a bolus or infusion schedule that every later run applies.
+    #[wasm_bindgen(js_name = addDosingSchedule)]
+    pub fn add_dosing_schedule(&mut self, schedule: JsValue) -> Result<(), JsValue> {
+        let schedule: simulator::DosingSchedule = serde_wasm_bindgen::from_value(schedule)
+            .map_err(|e| JsValue::from_str(&format!("Invalid dosing schedule: {}", e)))?;
+        self.simulator.add_dosing_schedule(schedule)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    #[wasm_bindgen(js_name = getDosingSchedules)]
+    pub fn get_dosing_schedules(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self.simulator.dosing_schedules())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize dosing schedules: {}", e)))
+    }
+    
+    #[wasm_bindgen(js_name = clearDosingSchedules)]
+    pub fn clear_dosing_schedules(&mut self) {
+        self.simulator.clear_dosing_schedules();
+    }
+    
+    /// Binds a species or parameter to a time series given as
+    /// `{ target, times, values, interpolation, extrapolation }`.
+    #[wasm_bindgen(js_name = bindForcing)]
+    pub fn bind_forcing(&mut self, forcing: JsValue) -> Result<(), JsValue> {
+        let forcing: simulator::Forcing = serde_wasm_bindgen::from_value(forcing)
+            .map_err(|e| JsValue::from_str(&format!("Invalid forcing: {}", e)))?;
+        self.simulator.bind_forcing(forcing)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    /// Binds a species or parameter to one column of CSV or TSV text.
+    #[wasm_bindgen(js_name = bindForcingCsv)]
+    pub fn bind_forcing_csv(&mut self, csv: &str, options: JsValue) -> Result<(), JsValue> {
+        let options: simulator::CsvForcingOptions = serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid forcing options: {}", e)))?;
+        let forcing = simulator::Forcing::from_csv(csv, options)
+            .map_err(|e| JsValue::from_str(&format!("Failed to read forcing data: {}", e)))?;
+        self.simulator.bind_forcing(forcing)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    #[wasm_bindgen(js_name = getForcings)]
+    pub fn get_forcings(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.simulator.forcings())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize forcings: {}", e)))
+    }
+    
+    #[wasm_bindgen(js_name = clearForcings)]
+    pub fn clear_forcings(&mut self) {
+        self.simulator.clear_forcings();
+    }
+    
+    /// Sets piecewise-constant values for a parameter from `[[t0, v0], [t1, v1], ...]`;
+    /// an empty list removes the schedule.
+    #[wasm_bindgen(js_name = setParameterSchedule)]
+    pub fn set_parameter_schedule(&mut self, param_id: &str, changes: JsValue) -> Result<(), JsValue> {
+        let changes: Vec<(f64, f64)> = serde_wasm_bindgen::from_value(changes)
+            .map_err(|e| JsValue::from_str(&format!("Invalid parameter schedule: {}", e)))?;
+        self.simulator.set_parameter_schedule(param_id, changes)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    #[wasm_bindgen(js_name = getParameterSchedules)]
+    pub fn get_parameter_schedules(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self.simulator.parameter_schedules())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize parameter schedules: {}", e)))
+    }
+    
+    #[wasm_bindgen(js_name = clearParameterSchedules)]
+    pub fn clear_parameter_schedules(&mut self) {
+        self.simulator.clear_parameter_schedules();
+    }
+    
+    /// Runs a Synthea-style YAML (or JSON) simulation spec. Its `inputs`
+    /// apply to this run only; the model is restored afterwards.
+    #[wasm_bindgen(js_name = runSpec)]
+    pub fn run_spec(&mut self, spec_text: &str, model_file: Option<String>) -> Result<JsValue, JsValue> {
+        let spec = spec::SimulationSpec::parse(spec_text)
+            .map_err(|e| JsValue::from_str(&e))?;
+        let model_warning = spec.check_model(&self.model_data, model_file.as_deref())
+            .map_err(|e| JsValue::from_str(&e))?;
+        let (method, warning) = spec.method()
+            .map_err(|e| JsValue::from_str(&e))?;
+        
+        let original = self.model_data.clone();
+        spec.apply_inputs(&mut self.model_data)
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.simulator.update_parameters(&self.model_data);
+        
+        let results = self.run(&SimulationConfig::from_spec(&spec, method));
+        
+        self.model_data = original;
+        self.simulator.update_parameters(&self.model_data);
+        
+        let results = results?;
+        let figures = spec.charts.iter()
+            .enumerate()
+            .map(|(k, chart)| {
+                let filename = chart.filename.as_deref()
+                    .map(chart::svg_filename)
+                    .unwrap_or_else(|| format!("chart_{}.svg", k + 1));
+                chart::render_svg(&results, chart)
+                    .map(|svg| Figure { filename, svg })
+                    .map_err(|e| JsValue::from_str(&format!("Chart {}: {}", k + 1, e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        let run = SpecRun {
+            name: spec.name,
+            model: spec.model,
+            method: method.to_string(),
+            warnings: model_warning.into_iter().chain(warning).chain(results.warnings.iter().cloned()).collect(),
+            results,
+            charts: spec.charts,
+            figures,
+        };
+        serde_wasm_bindgen::to_value(&run)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize spec run: {}", e)))
+    }
+    
+    #[wasm_bindgen(js_name = getConservationLaws)]
+    pub fn get_conservation_laws(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.simulator.conservation_laws())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize conservation laws: {}", e)))
+    }
+    
+    /// The SBML model id, when the model declares one.
+    #[wasm_bindgen(js_name = getModelId)]
+    pub fn get_model_id(&self) -> Option<String> {
+        self.model_data.id.clone()
+    }
+    
     #[wasm_bindgen(js_name = getSpeciesNames)]
     pub fn get_species_names(&self) -> Vec<String> {
         self.model_data.species.iter()
@@ -133,23 +350,34 @@ impl BioModel {
         Err(JsValue::from_str(&format!("Parameter '{}' not found", param_id)))
     }
     
+    /// Runs `config` once per value of `param_id`, as `simulate` would.
     #[wasm_bindgen(js_name = parameterScan)]
-    pub fn parameter_scan(&mut self, param_id: &str, values: Vec<f64>) -> Result<JsValue, JsValue> {
-        let original_value = self.model_data.parameters.iter()
-            .find(|p| p.id == param_id)
-            .ok_or_else(|| JsValue::from_str(&format!("Parameter '{}' not found", param_id)))?
-            .value;
+    pub fn parameter_scan(&mut self, param_id: &str, values: Vec<f64>, config: JsValue) -> Result<JsValue, JsValue> {
+        let config: SimulationConfig = serde_wasm_bindgen::from_value(config)
+            .map_err(|e| JsValue::from_str(&format!("Invalid config: {}", e)))?;
+        self.simulator.check_rate_parameter(param_id)
+            .map_err(|e| JsValue::from_str(&e))?;
+        let original_value = self.model_data.get_parameter_value(param_id);
         
+        self.configure(&config)?;
         let mut scan_results = Vec::new();
         
         for value in values {
             self.set_parameter(param_id, value)?;
             
-            let results = self.simulator.simulate(100.0, 0.1, "rk4")?;
-            scan_results.push(ScanResult {
-                parameter_value: value,
-                results,
-            });
+            // A diverging value is recorded and the scan moves on
+            match self.run_configured(&config) {
+                Ok(results) => scan_results.push(ScanResult {
+                    parameter_value: value,
+                    results: Some(results),
+                    error: None,
+                }),
+                Err(error) => scan_results.push(ScanResult {
+                    parameter_value: value,
+                    results: None,
+                    error: Some(error),
+                }),
+            }
         }
         
         self.set_parameter(param_id, original_value)?;
@@ -159,6 +387,69 @@ impl BioModel {
     }
 }
 
+impl BioModel {
+    fn run(&mut self, config: &SimulationConfig) -> Result<simulator::SimulationResults, JsValue> {
+        console_log!("Running simulation from t=0 to t={} with step {}", 
+            config.time_end, config.time_step);
+        
+        let num_steps = (config.time_end / config.time_step) as usize;
+        console_log!("This will generate {} time points", num_steps + 1);
+        
+        // Warn if too many points
+        if num_steps > 10000 {
+            console_log!("Warning: Large simulation with {} steps may be slow", num_steps);
+        }
+        
+        self.configure(config)?;
+        Ok(self.run_configured(config)?)
+    }
+    
+    /// Runs `config` with the options `configure` already applied.
+    fn run_configured(&mut self, config: &SimulationConfig) -> Result<simulator::SimulationResults, simulator::SimulationError> {
+        let mut results = self.simulator.simulate(
+            config.time_end,
+            config.time_step,
+            &config.method
+        )?;
+        config.annotate(&mut results);
+        self.record_overrides(&mut results);
+        
+        Ok(results)
+    }
+    
+    /// Adds the parameters whose values differ from the parsed model, and
+    /// that reach the run, to the run info of `results`.
+    fn record_overrides(&self, results: &mut simulator::SimulationResults) {
+        if let Some(info) = &mut results.run_info {
+            info.parameter_overrides = self.model_data.parameters.iter()
+                .zip(&self.parsed_parameters)
+                .filter(|(current, parsed)| current.value != parsed.value)
+                .filter(|(current, _)| self.simulator.parameter_applies(&current.id))
+                .map(|(current, _)| (current.id.clone(), current.value))
+                .collect();
+        }
+    }
+    
+    /// Applies the run options of `config` to the simulator.
+    fn configure(&mut self, config: &SimulationConfig) -> Result<(), JsValue> {
+        self.simulator.set_seed(config.seed);
+        self.simulator.set_stochastic_scale(config.stochastic_scale)
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.simulator.set_hybrid_options(simulator::HybridOptions {
+            fast_reactions: config.hybrid_fast_reactions.clone(),
+            threshold: config.hybrid_threshold,
+        }).map_err(|e| JsValue::from_str(&e))?;
+        self.simulator.set_conservation_reduction(config.reduce_conservation);
+        self.simulator.set_positivity(simulator::Positivity::parse(&config.positivity)
+            .map_err(|e| JsValue::from_str(&e))?);
+        self.simulator.set_divergence_threshold(config.divergence_threshold);
+        self.simulator.set_record_fluxes(config.record_fluxes);
+        self.simulator.set_observables(config.observables.iter()
+            .map(|(id, expression)| simulator::Observable { id: id.clone(), expression: expression.clone() })
+            .collect()).map_err(|e| JsValue::from_str(&e))
+    }
+}
+
 #[derive(serde::Deserialize)]
 struct SimulationConfig {
     #[serde(rename = "timeEnd")]
@@ -167,16 +458,540 @@ struct SimulationConfig {
     time_step: f64,
     #[serde(default = "default_method")]
     method: String,
+    #[serde(default)]
+    seed: Option<u64>,
+    /// Molecules per unit of substance for the stochastic methods.
+    #[serde(default = "default_stochastic_scale", rename = "stochasticScale")]
+    stochastic_scale: f64,
+    #[serde(default, rename = "hybridFastReactions")]
+    hybrid_fast_reactions: Vec<String>,
+    #[serde(default = "default_hybrid_threshold", rename = "hybridThreshold")]
+    hybrid_threshold: f64,
+    #[serde(default, rename = "reduceConservation")]
+    reduce_conservation: bool,
+    #[serde(default = "default_positivity")]
+    positivity: String,
+    #[serde(default = "default_divergence_threshold", rename = "divergenceThreshold")]
+    divergence_threshold: f64,
+    #[serde(default, rename = "timeUnit")]
+    time_unit: Option<timeline::TimeUnit>,
+    #[serde(default, rename = "gestationalStartWeek")]
+    gestational_start_week: Option<f64>,
+    /// Windows logged as events when they open or close during the run;
+    /// needs `gestationalStartWeek`.
+    #[serde(default, rename = "gestationalWindows")]
+    gestational_windows: Vec<timeline::GestationalWindow>,
+    #[serde(default, rename = "recordFluxes")]
+    record_fluxes: bool,
+    /// Observable id to infix or MathML expression.
+    #[serde(default)]
+    observables: std::collections::BTreeMap<String, String>,
+}
+
+impl SimulationConfig {
+    fn from_spec(spec: &spec::SimulationSpec, method: &str) -> Self {
+        SimulationConfig {
+            time_end: spec.duration,
+            time_step: spec.step_size,
+            method: method.to_string(),
+            seed: spec.seed,
+            stochastic_scale: default_stochastic_scale(),
+            hybrid_fast_reactions: Vec::new(),
+            hybrid_threshold: default_hybrid_threshold(),
+            reduce_conservation: false,
+            positivity: default_positivity(),
+            divergence_threshold: default_divergence_threshold(),
+            time_unit: Some(spec.time_unit),
+            gestational_start_week: spec.gestational_start_week,
+            gestational_windows: Vec::new(),
+            record_fluxes: false,
+            observables: Default::default(),
+        }
+    }
+    
+    /// Adds the time unit, gestational-week axis and window events to
+    /// `results`.
+    fn annotate(&self, results: &mut simulator::SimulationResults) {
+        results.time_unit = self.time_unit;
+        if let Some(start_week) = self.gestational_start_week {
+            let mut timeline = timeline::GestationalTimeline::new(self.time_unit.unwrap_or_default(), start_week);
+            timeline.windows = self.gestational_windows.clone();
+            results.gestational_week = Some(timeline.weeks(&results.time));
+            if let (Some(&from), Some(&to)) = (results.time.first(), results.time.last()) {
+                simulator::events::log_windows(&mut results.events, &timeline, from, to);
+            }
+        }
+    }
 }
 
 fn default_method() -> String {
     "rk4".to_string()
 }
 
+fn default_stochastic_scale() -> f64 {
+    1.0
+}
+
+fn default_positivity() -> String {
+    "clip".to_string()
+}
+
+fn default_divergence_threshold() -> f64 {
+    simulator::divergence::DEFAULT_DIVERGENCE_THRESHOLD
+}
+
+fn default_hybrid_threshold() -> f64 {
+    simulator::hybrid::DEFAULT_HYBRID_THRESHOLD
+}
+
+#[derive(serde::Serialize)]
+struct SpecRun {
+    name: Option<String>,
+    model: Option<String>,
+    method: String,
+    warnings: Vec<String>,
+    results: simulator::SimulationResults,
+    charts: Vec<spec::ChartSpec>,
+    figures: Vec<Figure>,
+}
+
+#[derive(serde::Serialize)]
+struct FluxColumns<'a> {
+    rates: &'a [f64],
+    cumulative: &'a [f64],
+}
+
+#[derive(serde::Serialize)]
+struct Figure {
+    filename: String,
+    svg: String,
+}
+
 #[derive(serde::Serialize)]
 struct ScanResult {
     parameter_value: f64,
+    results: Option<simulator::SimulationResults>,
+    error: Option<simulator::SimulationError>,
+}
+
+/// Renders simulation results (as returned by `simulate`) as an SVG line
+/// chart described by a config-style chart spec.
+#[wasm_bindgen(js_name = renderChart)]
+pub fn render_chart(results: JsValue, chart: JsValue) -> Result<String, JsValue> {
+    let results: simulator::SimulationResults = serde_wasm_bindgen::from_value(results)
+        .map_err(|e| JsValue::from_str(&format!("Invalid results: {}", e)))?;
+    let chart: spec::ChartSpec = serde_wasm_bindgen::from_value(chart)
+        .map_err(|e| JsValue::from_str(&format!("Invalid chart spec: {}", e)))?;
+    chart::render_svg(&results, &chart)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Writes simulation results as CSV or TSV text.
+#[wasm_bindgen(js_name = toCSV)]
+pub fn to_csv(results: JsValue, options: JsValue) -> Result<String, JsValue> {
+    let results: simulator::SimulationResults = serde_wasm_bindgen::from_value(results)
+        .map_err(|e| JsValue::from_str(&format!("Invalid results: {}", e)))?;
+    export::to_csv(&results, &csv_options(options)?)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+fn csv_options(options: JsValue) -> Result<export::CsvOptions, JsValue> {
+    if options.is_undefined() || options.is_null() {
+        Ok(export::CsvOptions::default())
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid CSV options: {}", e)))
+    }
+}
+
+/// Reads CSV or TSV text (saved runs or experimental tables) into the
+/// results shape returned by `simulate`.
+#[wasm_bindgen(js_name = parseCSV)]
+pub fn parse_csv(text: &str, time_column: Option<String>, delimiter: Option<String>) -> Result<JsValue, JsValue> {
+    let delimiter = delimiter.as_deref().map(single_char).transpose()?;
+    let results = export::from_csv(text, time_column.as_deref(), delimiter)
+        .map_err(|e| JsValue::from_str(&format!("Failed to read table: {}", e)))?;
+    serde_wasm_bindgen::to_value(&results)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize results: {}", e)))
+}
+
+/// `text` as a delimiter, which must be a single character.
+fn single_char(text: &str) -> Result<char, JsValue> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(JsValue::from_str(&format!("Delimiter must be a single character, got {:?}", text))),
+    }
+}
+
+/// Non-compartmental PK metrics (Cmax, Tmax, AUC, terminal half-life, MRT,
+/// time above threshold) for each of `species_ids`, per dose interval too
+/// when the run had a dosing schedule.
+#[wasm_bindgen(js_name = pkSummary)]
+pub fn pk_summary(results: JsValue, species_ids: Vec<String>, options: JsValue) -> Result<JsValue, JsValue> {
+    let results: simulator::SimulationResults = serde_wasm_bindgen::from_value(results)
+        .map_err(|e| JsValue::from_str(&format!("Invalid results: {}", e)))?;
+    pk_summary_of(&results, &species_ids, options)
+}
+
+fn pk_summary_of(results: &simulator::SimulationResults, species_ids: &[String], options: JsValue) -> Result<JsValue, JsValue> {
+    let options: analysis::PkOptions = if options.is_undefined() || options.is_null() {
+        analysis::PkOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid PK options: {}", e)))?
+    };
+    let summary = analysis::pk_summary(results, species_ids, &options)
+        .map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&summary)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize PK summary: {}", e)))
+}
+
+/// Writes simulation results as an Arrow IPC stream with a `run_id`
+/// column ("0"), `time`, and one column per species. `info` is the config
+/// given to `simulate`, optionally with `parameterOverrides`; without it
+/// the run info recorded in the results is used.
+#[wasm_bindgen(js_name = toArrowIPC)]
+pub fn to_arrow_ipc(results: JsValue, model_id: Option<String>, info: JsValue) -> Result<Vec<u8>, JsValue> {
+    let results: simulator::SimulationResults = serde_wasm_bindgen::from_value(results)
+        .map_err(|e| JsValue::from_str(&format!("Invalid results: {}", e)))?;
+    export::arrow::to_arrow_ipc(&results, model_id, run_info(info)?)
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+fn run_info(info: JsValue) -> Result<Option<export::RunInfo>, JsValue> {
+    if info.is_undefined() || info.is_null() {
+        Ok(None)
+    } else {
+        serde_wasm_bindgen::from_value(info)
+            .map(Some)
+            .map_err(|e| JsValue::from_str(&format!("Invalid run info: {}", e)))
+    }
+}
+
+fn parse_interpolation(interpolation: Option<String>) -> Result<simulator::Interpolation, JsValue> {
+    interpolation.map_or(Ok(simulator::Interpolation::Linear), |name| simulator::Interpolation::parse(&name))
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Simulation results kept in WASM memory.
+///
+/// `time`, `values`, `gestationalWeek` and the per-species columns return
+/// `Float64Array` views straight over WASM memory, so nothing is copied.
+/// A view is only valid until WASM memory next grows (any later call into
+/// the module may do that) and until the handle is freed; call `.slice()`
+/// on it to keep a copy.
+#[wasm_bindgen]
+pub struct SimulationHandle {
     results: simulator::SimulationResults,
+    /// Species trajectories, extracted from the row-major values on first
+    /// access.
+    columns: RefCell<Vec<Option<Vec<f64>>>>,
+}
+
+impl SimulationHandle {
+    fn new(results: simulator::SimulationResults) -> Self {
+        let columns = RefCell::new(vec![None; results.num_species]);
+        SimulationHandle { results, columns }
+    }
+    
+    fn flux(&self, reaction_id: &str) -> Result<&simulator::ReactionFlux, JsValue> {
+        if self.results.fluxes.is_empty() {
+            return Err(JsValue::from_str("No fluxes recorded; set recordFluxes in the config"));
+        }
+        self.results.flux(reaction_id)
+            .ok_or_else(|| JsValue::from_str(&format!("Reaction '{}' not found", reaction_id)))
+    }
+    
+    fn column(&self, index: usize) -> js_sys::Float64Array {
+        let mut columns = self.columns.borrow_mut();
+        let column = columns[index].get_or_insert_with(|| self.results.get_species_trajectory(index));
+        // SAFETY: the column is never modified or reallocated while the
+        // handle lives; validity of the view is documented on the type.
+        unsafe { js_sys::Float64Array::view(column) }
+    }
+}
+
+#[wasm_bindgen]
+impl SimulationHandle {
+    #[wasm_bindgen(getter, js_name = numTimePoints)]
+    pub fn num_time_points(&self) -> usize {
+        self.results.time.len()
+    }
+    
+    #[wasm_bindgen(getter, js_name = numSpecies)]
+    pub fn num_species(&self) -> usize {
+        self.results.num_species
+    }
+    
+    #[wasm_bindgen(js_name = speciesIds)]
+    pub fn species_ids(&self) -> Vec<String> {
+        self.results.species_ids.clone()
+    }
+    
+    #[wasm_bindgen(js_name = speciesNames)]
+    pub fn species_names(&self) -> Vec<String> {
+        self.results.species_names.clone()
+    }
+    
+    pub fn time(&self) -> js_sys::Float64Array {
+        // SAFETY: see `column`.
+        unsafe { js_sys::Float64Array::view(&self.results.time) }
+    }
+    
+    /// The whole matrix, row-major with shape `[numTimePoints, numSpecies]`.
+    pub fn values(&self) -> js_sys::Float64Array {
+        // SAFETY: see `column`.
+        unsafe { js_sys::Float64Array::view(&self.results.values) }
+    }
+    
+    #[wasm_bindgen(js_name = gestationalWeek)]
+    pub fn gestational_week(&self) -> Option<js_sys::Float64Array> {
+        // SAFETY: see `column`.
+        self.results.gestational_week.as_ref()
+            .map(|weeks| unsafe { js_sys::Float64Array::view(weeks) })
+    }
+    
+    /// Trajectory of one species, by id or name.
+    pub fn species(&self, species: &str) -> Result<js_sys::Float64Array, JsValue> {
+        let index = self.results.species_index(species)
+            .ok_or_else(|| JsValue::from_str(&format!("Species '{}' not found", species)))?;
+        Ok(self.column(index))
+    }
+    
+    #[wasm_bindgen(js_name = speciesAt)]
+    pub fn species_at(&self, index: usize) -> Result<js_sys::Float64Array, JsValue> {
+        if index >= self.results.num_species {
+            return Err(JsValue::from_str(&format!("Species index {} out of range", index)));
+        }
+        Ok(self.column(index))
+    }
+    
+    /// Ids of the recorded compartments and non-constant parameters.
+    #[wasm_bindgen(js_name = variableIds)]
+    pub fn variable_ids(&self) -> Vec<String> {
+        self.results.variables.iter().map(|v| v.id.clone()).collect()
+    }
+    
+    /// Trajectory of a species or recorded variable, by id or name.
+    pub fn trajectory(&self, id: &str) -> Result<js_sys::Float64Array, JsValue> {
+        if let Some(index) = self.results.species_index(id) {
+            return Ok(self.column(index));
+        }
+        let v = self.results.variable_index(id)
+            .ok_or_else(|| JsValue::from_str(&format!("'{}' is not a species or variable in the results", id)))?;
+        // SAFETY: see `column`.
+        Ok(unsafe { js_sys::Float64Array::view(&self.results.variables[v].values) })
+    }
+    
+    /// Ids of the reactions with recorded fluxes.
+    #[wasm_bindgen(js_name = reactionIds)]
+    pub fn reaction_ids(&self) -> Vec<String> {
+        self.results.fluxes.iter().map(|f| f.reaction_id.clone()).collect()
+    }
+    
+    /// Rates and cumulative flux of every reaction, keyed by reaction id:
+    /// `{ [id]: { rates, cumulative } }`. Requires `recordFluxes`.
+    pub fn fluxes(&self) -> Result<JsValue, JsValue> {
+        let fluxes: std::collections::BTreeMap<&str, FluxColumns> = self.results.fluxes.iter()
+            .map(|f| (f.reaction_id.as_str(), FluxColumns { rates: &f.rates, cumulative: &f.cumulative }))
+            .collect();
+        serde_wasm_bindgen::to_value(&fluxes)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize fluxes: {}", e)))
+    }
+    
+    /// Rate of one reaction at each time point, as a `Float64Array` view.
+    #[wasm_bindgen(js_name = fluxRates)]
+    pub fn flux_rates(&self, reaction_id: &str) -> Result<js_sys::Float64Array, JsValue> {
+        let flux = self.flux(reaction_id)?;
+        // SAFETY: see `column`.
+        Ok(unsafe { js_sys::Float64Array::view(&flux.rates) })
+    }
+    
+    /// Integral of one reaction's rate from the first time point, as a
+    /// `Float64Array` view.
+    #[wasm_bindgen(js_name = cumulativeFlux)]
+    pub fn cumulative_flux(&self, reaction_id: &str) -> Result<js_sys::Float64Array, JsValue> {
+        let flux = self.flux(reaction_id)?;
+        // SAFETY: see `column`.
+        Ok(unsafe { js_sys::Float64Array::view(&flux.cumulative) })
+    }
+    
+    /// The event log: `[{ time, kind, id, assignments: [{ variable, before,
+    /// after }] }]` in time order.
+    pub fn events(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.results.events)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize events: {}", e)))
+    }
+    
+    /// Value of a species or variable at time `t`, interpolated `linear`
+    /// (default), `step` or `monotone_cubic` between output points.
+    #[wasm_bindgen(js_name = valueAt)]
+    pub fn value_at(&self, id: &str, t: f64, interpolation: Option<String>) -> Result<f64, JsValue> {
+        self.results.value_at(id, t, parse_interpolation(interpolation)?)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    /// A new handle holding the output points with `t0 <= t <= t1`.
+    pub fn slice(&self, t0: f64, t1: f64) -> Result<SimulationHandle, JsValue> {
+        self.results.slice(t0, t1)
+            .map(SimulationHandle::new)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    /// A new handle with every column interpolated onto a uniform grid of
+    /// step `dt`.
+    pub fn resample(&self, dt: f64, interpolation: Option<String>) -> Result<SimulationHandle, JsValue> {
+        self.results.resample(dt, parse_interpolation(interpolation)?)
+            .map(SimulationHandle::new)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    /// Everything except the numeric arrays: incidents, events, partitions,
+    /// conservation laws, schedules and units.
+    pub fn metadata(&self) -> Result<JsValue, JsValue> {
+        let metadata = simulator::SimulationResults {
+            time: Vec::new(),
+            values: Vec::new(),
+            variables: Vec::new(),
+            fluxes: Vec::new(),
+            gestational_week: None,
+            ..self.results.clone()
+        };
+        serde_wasm_bindgen::to_value(&metadata)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize metadata: {}", e)))
+    }
+    
+    /// The full results as a plain object, as `simulate` returns them.
+    #[wasm_bindgen(js_name = toObject)]
+    pub fn to_object(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.results)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize results: {}", e)))
+    }
+    
+    #[wasm_bindgen(js_name = toCSV)]
+    pub fn to_csv(&self, options: JsValue) -> Result<String, JsValue> {
+        export::to_csv(&self.results, &csv_options(options)?)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    #[wasm_bindgen(js_name = renderChart)]
+    pub fn render_chart(&self, chart: JsValue) -> Result<String, JsValue> {
+        let chart: spec::ChartSpec = serde_wasm_bindgen::from_value(chart)
+            .map_err(|e| JsValue::from_str(&format!("Invalid chart spec: {}", e)))?;
+        chart::render_svg(&self.results, &chart)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    #[wasm_bindgen(js_name = pkSummary)]
+    pub fn pk_summary(&self, species_ids: Vec<String>, options: JsValue) -> Result<JsValue, JsValue> {
+        pk_summary_of(&self.results, &species_ids, options)
+    }
+    
+    #[wasm_bindgen(js_name = toArrowIPC)]
+    pub fn to_arrow_ipc(&self, model_id: Option<String>, info: JsValue) -> Result<Vec<u8>, JsValue> {
+        export::arrow::to_arrow_ipc(&self.results, model_id, run_info(info)?)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+/// Several runs of one model collected into a single Arrow IPC stream, one
+/// record batch per run, tagged by a `run_id` column.
+#[wasm_bindgen]
+pub struct ArrowEnsemble {
+    ensemble: export::ArrowEnsemble,
+}
+
+#[wasm_bindgen]
+impl ArrowEnsemble {
+    #[wasm_bindgen(constructor)]
+    pub fn new(model_id: Option<String>) -> ArrowEnsemble {
+        ArrowEnsemble { ensemble: export::ArrowEnsemble::new(model_id) }
+    }
+    
+    #[wasm_bindgen(getter, js_name = numRuns)]
+    pub fn num_runs(&self) -> usize {
+        self.ensemble.num_runs()
+    }
+    
+    /// Appends results as returned by `simulate`. `info` is as for
+    /// `toArrowIPC`.
+    #[wasm_bindgen(js_name = addRun)]
+    pub fn add_run(&mut self, run_id: &str, results: JsValue, info: JsValue) -> Result<(), JsValue> {
+        let results: simulator::SimulationResults = serde_wasm_bindgen::from_value(results)
+            .map_err(|e| JsValue::from_str(&format!("Invalid results: {}", e)))?;
+        self.ensemble.add_run(run_id, &results, run_info(info)?)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    #[wasm_bindgen(js_name = addHandle)]
+    pub fn add_handle(&mut self, run_id: &str, handle: &SimulationHandle, info: JsValue) -> Result<(), JsValue> {
+        self.ensemble.add_run(run_id, &handle.results, run_info(info)?)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
+    #[wasm_bindgen(js_name = toIPC)]
+    pub fn to_ipc(&self) -> Result<Vec<u8>, JsValue> {
+        self.ensemble.to_ipc()
+            .map_err(|e| JsValue::from_str(&e))
+    }
+}
+
+/// Maps simulation time to gestational week, trimester and named windows.
+#[wasm_bindgen]
+pub struct GestationalClock {
+    timeline: timeline::GestationalTimeline,
+}
+
+#[wasm_bindgen]
+impl GestationalClock {
+    /// `options` is `{ timeUnit, startWeek, windows: [{ name, startWeek, endWeek }] }`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: JsValue) -> Result<GestationalClock, JsValue> {
+        let timeline = serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid gestational clock options: {}", e)))?;
+        Ok(GestationalClock { timeline })
+    }
+    
+    pub fn week(&self, time: f64) -> f64 {
+        self.timeline.week(time)
+    }
+    
+    pub fn trimester(&self, time: f64) -> u8 {
+        self.timeline.trimester(time)
+    }
+    
+    #[wasm_bindgen(js_name = activeWindows)]
+    pub fn active_windows(&self, time: f64) -> Vec<String> {
+        self.timeline.active_windows(time)
+    }
+    
+    /// `{ week, trimester, windows }` for one time point.
+    pub fn at(&self, time: f64) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(&self.timeline.point(time))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize gestational point: {}", e)))
+    }
+    
+    #[wasm_bindgen(js_name = timeAtWeek)]
+    pub fn time_at_week(&self, week: f64) -> f64 {
+        self.timeline.time_at_week(week)
+    }
+    
+    /// `[start, end]` of a named window in simulation time.
+    #[wasm_bindgen(js_name = windowTimes)]
+    pub fn window_times(&self, name: &str) -> Result<Vec<f64>, JsValue> {
+        self.timeline.window_times(name)
+            .map(|(start, end)| vec![start, end])
+            .ok_or_else(|| JsValue::from_str(&format!("Window '{}' not found", name)))
+    }
+    
+    /// Converts `value` between 'hour', 'day' and 'week'.
+    #[wasm_bindgen(js_name = convertTime)]
+    pub fn convert_time(value: f64, from: &str, to: &str) -> Result<f64, JsValue> {
+        let from = timeline::TimeUnit::parse(from).map_err(|e| JsValue::from_str(&e))?;
+        let to = timeline::TimeUnit::parse(to).map_err(|e| JsValue::from_str(&e))?;
+        Ok(from.convert(value, to))
+    }
 }
 
 #[wasm_bindgen(start)]
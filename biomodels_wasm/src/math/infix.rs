@@ -0,0 +1,294 @@
+use super::{Expr, Op};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    /// Operators and punctuation.
+    Punct(&'static str),
+}
+
+/// Operators, longest first so that `<=` is not read as `<`.
+const PUNCTUATION: [&str; 19] = [
+    "&&", "||", "<=", ">=", "==", "!=",
+    "+", "-", "*", "/", "^", "(", ")", ",", "<", ">", "!", "=", "%",
+];
+
+/// Parses an infix expression such as `GSH / (GSH + GSSG)`.
+///
+/// Identifiers are model ids; `time` is the simulation time and `pi` the
+/// constant. Operators are `+ - * / % ^`, comparisons, `&&`/`and`,
+/// `||`/`or` and `!`/`not`, with the usual precedence and `^` binding
+/// tighter than unary minus. The functions are exp, ln, log (base 10),
+/// log10, sqrt, abs, floor, ceil, min, max, pow and
+/// `piecewise(value1, condition1, ..., otherwise)`.
+pub fn parse_infix(text: &str) -> Result<Expr, String> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser { tokens, position: 0 };
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("Unexpected {} in '{}'", describe(token), text)),
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_digit() || (c == '.' && rest[1..].starts_with(|d: char| d.is_ascii_digit())) {
+            let end = number_end(rest);
+            let number = rest[..end].parse()
+                .map_err(|_| format!("Invalid number '{}'", &rest[..end]))?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if let Some(p) = PUNCTUATION.iter().find(|p| rest.starts_with(*p)) {
+            tokens.push(Token::Punct(p));
+            rest = &rest[p.len()..];
+        } else {
+            return Err(format!("Unexpected character '{}' in '{}'", c, text));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Length of the number at the start of `text`: digits, a fraction and an
+/// optional exponent.
+fn number_end(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let digits = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+    let mut end = digits(0);
+    if end < bytes.len() && bytes[end] == b'.' {
+        end = digits(end + 1);
+    }
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut i = end + 1;
+        if i < bytes.len() && (bytes[i] == b'+' || bytes[i] == b'-') {
+            i += 1;
+        }
+        if i < bytes.len() && bytes[i].is_ascii_digit() {
+            end = digits(i);
+        }
+    }
+    end
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(x) => format!("number {}", x),
+        Token::Ident(id) => format!("'{}'", id),
+        Token::Punct(p) => format!("'{}'", p),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+    
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+    
+    /// Consumes the next token if it is one of `ops` (punctuation or word
+    /// operators), returning which.
+    fn eat(&mut self, ops: &[&str]) -> Option<String> {
+        let matched = match self.peek()? {
+            Token::Punct(p) => ops.iter().find(|op| *op == p),
+            Token::Ident(word) => ops.iter().find(|op| *op == word),
+            Token::Number(_) => None,
+        }?;
+        self.position += 1;
+        Some(matched.to_string())
+    }
+    
+    fn expect(&mut self, punct: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Punct(p)) if p == punct => Ok(()),
+            Some(token) => Err(format!("Expected '{}' but found {}", punct, describe(&token))),
+            None => Err(format!("Expected '{}' but the expression ended", punct)),
+        }
+    }
+    
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.eat(&["||", "or"]).is_some() {
+            left = Expr::Apply(Op::Or, vec![left, self.and()?]);
+        }
+        Ok(left)
+    }
+    
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.comparison()?;
+        while self.eat(&["&&", "and"]).is_some() {
+            left = Expr::Apply(Op::And, vec![left, self.comparison()?]);
+        }
+        Ok(left)
+    }
+    
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.additive()?;
+        let op = match self.eat(&["<", ">", "<=", ">=", "==", "!=", "="]).as_deref() {
+            Some("<") => Op::Lt,
+            Some(">") => Op::Gt,
+            Some("<=") => Op::Leq,
+            Some(">=") => Op::Geq,
+            Some("==") | Some("=") => Op::Eq,
+            Some("!=") => Op::Neq,
+            _ => return Ok(left),
+        };
+        Ok(Expr::Apply(op, vec![left, self.additive()?]))
+    }
+    
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut left = self.multiplicative()?;
+        while let Some(op) = self.eat(&["+", "-"]) {
+            let op = if op == "+" { Op::Plus } else { Op::Minus };
+            left = Expr::Apply(op, vec![left, self.multiplicative()?]);
+        }
+        Ok(left)
+    }
+    
+    fn multiplicative(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.eat(&["*", "/", "%"]) {
+            let right = self.unary()?;
+            left = match op.as_str() {
+                "*" => Expr::Apply(Op::Times, vec![left, right]),
+                "/" => Expr::Apply(Op::Divide, vec![left, right]),
+                // a % b = a - b * floor(a / b)
+                _ => Expr::Apply(Op::Minus, vec![
+                    left.clone(),
+                    Expr::Apply(Op::Times, vec![
+                        right.clone(),
+                        Expr::Apply(Op::Floor, vec![Expr::Apply(Op::Divide, vec![left, right])]),
+                    ]),
+                ]),
+            };
+        }
+        Ok(left)
+    }
+    
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.eat(&["-", "+", "!", "not"]).as_deref() {
+            Some("-") => Ok(Expr::Apply(Op::Minus, vec![self.unary()?])),
+            Some("+") => self.unary(),
+            Some(_) => Ok(Expr::Apply(Op::Not, vec![self.unary()?])),
+            None => self.power(),
+        }
+    }
+    
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.primary()?;
+        if self.eat(&["^"]).is_some() {
+            // Right-associative, and the exponent may carry its own sign
+            let exponent = self.unary()?;
+            return Ok(Expr::Apply(Op::Power, vec![base, exponent]));
+        }
+        Ok(base)
+    }
+    
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(x)) => Ok(Expr::Number(x)),
+            Some(Token::Punct("(")) => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => {
+                if self.eat(&["("]).is_some() {
+                    let args = self.arguments()?;
+                    return call(&name, args);
+                }
+                Ok(match name.as_str() {
+                    "time" => Expr::Time,
+                    "pi" => Expr::Number(std::f64::consts::PI),
+                    "true" => Expr::Number(1.0),
+                    "false" => Expr::Number(0.0),
+                    "inf" | "infinity" => Expr::Number(f64::INFINITY),
+                    _ => Expr::Symbol(name),
+                })
+            }
+            Some(token) => Err(format!("Unexpected {}", describe(&token))),
+            None => Err("Expression ended unexpectedly".to_string()),
+        }
+    }
+    
+    /// Comma-separated arguments after an opening parenthesis.
+    fn arguments(&mut self) -> Result<Vec<Expr>, String> {
+        let mut args = Vec::new();
+        if self.eat(&[")"]).is_some() {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.or()?);
+            if self.eat(&[")"]).is_some() {
+                return Ok(args);
+            }
+            self.expect(",")?;
+        }
+    }
+}
+
+fn call(name: &str, mut args: Vec<Expr>) -> Result<Expr, String> {
+    let arity = |n: usize| {
+        if args.len() == n {
+            Ok(())
+        } else {
+            Err(format!("{}() takes {} argument{}, got {}", name, n, if n == 1 { "" } else { "s" }, args.len()))
+        }
+    };
+    let op = match name {
+        "exp" => Op::Exp,
+        "ln" => Op::Ln,
+        "log" | "log10" => Op::Log10,
+        "sqrt" => Op::Sqrt,
+        "abs" => Op::Abs,
+        "floor" => Op::Floor,
+        "ceil" | "ceiling" => Op::Ceiling,
+        "min" | "max" => {
+            if args.is_empty() {
+                return Err(format!("{}() needs at least one argument", name));
+            }
+            let op = if name == "min" { Op::Min } else { Op::Max };
+            return Ok(Expr::Apply(op, args));
+        }
+        "pow" => {
+            arity(2)?;
+            return Ok(Expr::Apply(Op::Power, args));
+        }
+        "piecewise" => {
+            if args.is_empty() {
+                return Err("piecewise() needs at least one argument".to_string());
+            }
+            let otherwise = if args.len() % 2 == 1 { args.pop().map(Box::new) } else { None };
+            let mut pieces = Vec::new();
+            let mut args = args.into_iter();
+            while let (Some(value), Some(condition)) = (args.next(), args.next()) {
+                pieces.push((value, condition));
+            }
+            return Ok(Expr::Piecewise(pieces, otherwise));
+        }
+        _ => return Err(format!("Unknown function '{}'", name)),
+    };
+    arity(1)?;
+    Ok(Expr::Apply(op, args))
+}
@@ -0,0 +1,156 @@
+use serde::{Serialize, Deserialize};
+
+pub mod infix;
+
+pub use infix::parse_infix;
+
+/// A parsed mathematical expression, as found in SBML kinetic laws.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Expr {
+    Number(f64),
+    Symbol(String),
+    /// The SBML `time` csymbol.
+    Time,
+    Apply(Op, Vec<Expr>),
+    /// The SBML `delay` csymbol: the value of the first expression the
+    /// second expression's worth of time ago.
+    Delay(Box<Expr>, Box<Expr>),
+    /// (value, condition) pieces and the `otherwise` value.
+    Piecewise(Vec<(Expr, Expr)>, Option<Box<Expr>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Op {
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Power,
+    Exp,
+    Ln,
+    Log10,
+    Sqrt,
+    Abs,
+    Floor,
+    Ceiling,
+    Min,
+    Max,
+    Lt,
+    Gt,
+    Leq,
+    Geq,
+    Eq,
+    Neq,
+    And,
+    Or,
+    Not,
+}
+
+/// Where an expression looks up identifiers, the current time and the past.
+pub trait Scope {
+    fn value(&self, id: &str) -> Option<f64>;
+    fn time(&self) -> f64;
+    /// `expr` evaluated `tau` time units before `time()`.
+    fn delayed(&self, expr: &Expr, tau: f64) -> f64;
+}
+
+impl Expr {
+    /// Evaluates the expression. Unknown identifiers give NaN so that a
+    /// broken model shows up in the simulator's divergence checks.
+    pub fn eval(&self, scope: &dyn Scope) -> f64 {
+        match self {
+            Expr::Number(x) => *x,
+            Expr::Symbol(id) => scope.value(id).unwrap_or(f64::NAN),
+            Expr::Time => scope.time(),
+            Expr::Delay(expr, tau) => scope.delayed(expr, tau.eval(scope)),
+            Expr::Piecewise(pieces, otherwise) => {
+                for (value, condition) in pieces {
+                    if condition.eval(scope) != 0.0 {
+                        return value.eval(scope);
+                    }
+                }
+                otherwise.as_ref().map(|e| e.eval(scope)).unwrap_or(f64::NAN)
+            }
+            Expr::Apply(op, args) => {
+                let values: Vec<f64> = args.iter().map(|a| a.eval(scope)).collect();
+                apply(*op, &values)
+            }
+        }
+    }
+    
+    /// Identifiers the expression refers to, in order of appearance.
+    pub fn symbols(&self) -> Vec<&str> {
+        match self {
+            Expr::Symbol(id) => vec![id.as_str()],
+            Expr::Number(_) | Expr::Time => Vec::new(),
+            Expr::Delay(expr, tau) => [expr.symbols(), tau.symbols()].concat(),
+            Expr::Apply(_, args) => args.iter().flat_map(Expr::symbols).collect(),
+            Expr::Piecewise(pieces, otherwise) => pieces.iter()
+                .flat_map(|(v, c)| [v.symbols(), c.symbols()].concat())
+                .chain(otherwise.iter().flat_map(|e| e.symbols()))
+                .collect(),
+        }
+    }
+    
+    /// Whether the value changes with time alone: the expression reads the
+    /// clock, or past values through `delay`.
+    pub fn contains_time(&self) -> bool {
+        match self {
+            Expr::Time | Expr::Delay(_, _) => true,
+            Expr::Apply(_, args) => args.iter().any(Expr::contains_time),
+            Expr::Piecewise(pieces, otherwise) => {
+                pieces.iter().any(|(v, c)| v.contains_time() || c.contains_time())
+                    || otherwise.as_ref().is_some_and(|e| e.contains_time())
+            }
+            _ => false,
+        }
+    }
+    
+    pub fn contains_delay(&self) -> bool {
+        match self {
+            Expr::Delay(_, _) => true,
+            Expr::Apply(_, args) => args.iter().any(Expr::contains_delay),
+            Expr::Piecewise(pieces, otherwise) => {
+                pieces.iter().any(|(v, c)| v.contains_delay() || c.contains_delay())
+                    || otherwise.as_ref().is_some_and(|e| e.contains_delay())
+            }
+            _ => false,
+        }
+    }
+}
+
+fn truth(b: bool) -> f64 {
+    if b { 1.0 } else { 0.0 }
+}
+
+fn apply(op: Op, v: &[f64]) -> f64 {
+    let first = v.first().copied().unwrap_or(f64::NAN);
+    let second = v.get(1).copied().unwrap_or(f64::NAN);
+    
+    match op {
+        Op::Plus => v.iter().sum(),
+        Op::Minus if v.len() == 1 => -first,
+        Op::Minus => first - second,
+        Op::Times => v.iter().product(),
+        Op::Divide => first / second,
+        Op::Power => first.powf(second),
+        Op::Exp => first.exp(),
+        Op::Ln => first.ln(),
+        Op::Log10 => first.log10(),
+        Op::Sqrt => first.sqrt(),
+        Op::Abs => first.abs(),
+        Op::Floor => first.floor(),
+        Op::Ceiling => first.ceil(),
+        Op::Min => v.iter().copied().fold(f64::INFINITY, f64::min),
+        Op::Max => v.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        Op::Lt => truth(v.windows(2).all(|w| w[0] < w[1])),
+        Op::Gt => truth(v.windows(2).all(|w| w[0] > w[1])),
+        Op::Leq => truth(v.windows(2).all(|w| w[0] <= w[1])),
+        Op::Geq => truth(v.windows(2).all(|w| w[0] >= w[1])),
+        Op::Eq => truth(v.windows(2).all(|w| w[0] == w[1])),
+        Op::Neq => truth(first != second),
+        Op::And => truth(v.iter().all(|&x| x != 0.0)),
+        Op::Or => truth(v.iter().any(|&x| x != 0.0)),
+        Op::Not => truth(first == 0.0),
+    }
+}
@@ -1,20 +1,36 @@
 use serde::{Serialize, Deserialize};
 
+use crate::math::Expr;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BioModelData {
+    /// The SBML `<model id>`, when declared.
+    #[serde(default)]
+    pub id: Option<String>,
     pub compartments: Vec<Compartment>,
     pub species: Vec<Species>,
     pub reactions: Vec<Reaction>,
     pub parameters: Vec<Parameter>,
+    /// Variables set by SBML assignment or rate rules.
+    #[serde(default)]
+    pub rules: Vec<RuleTarget>,
+}
+
+impl Default for BioModelData {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BioModelData {
     pub fn new() -> Self {
         BioModelData {
+            id: None,
             compartments: Vec::new(),
             species: Vec::new(),
             reactions: Vec::new(),
             parameters: Vec::new(),
+            rules: Vec::new(),
         }
     }
     
@@ -22,6 +38,24 @@ impl BioModelData {
         self.species.iter().position(|s| s.id == species_id)
     }
     
+    /// Size of the compartment holding `species_id`, defaulting to 1.0 when
+    /// the compartment declares no size.
+    pub fn get_species_volume(&self, species_id: &str) -> f64 {
+        self.species.iter()
+            .find(|s| s.id == species_id)
+            .and_then(|s| self.compartments.iter().find(|c| c.id == s.compartment))
+            .map(|c| c.size)
+            .unwrap_or(1.0)
+    }
+    
+    /// Whether a parsed kinetic law references parameter `param_id`, i.e.
+    /// whether its value reaches any rate.
+    pub fn parameter_in_laws(&self, param_id: &str) -> bool {
+        self.reactions.iter()
+            .filter_map(|r| r.kinetic_math.as_ref())
+            .any(|expr| expr.symbols().contains(&param_id))
+    }
+    
     pub fn get_parameter_value(&self, param_id: &str) -> f64 {
         self.parameters.iter()
             .find(|p| p.id == param_id)
@@ -34,6 +68,7 @@ impl BioModelData {
 pub struct Compartment {
     pub id: String,
     pub name: String,
+    pub size: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +87,10 @@ pub struct Reaction {
     pub products: Vec<String>,
     pub rate_constant: f64,
     pub kinetic_law: String,
+    /// Parsed MathML of the kinetic law, when it uses only supported
+    /// constructs.
+    #[serde(default)]
+    pub kinetic_math: Option<Expr>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,4 +98,67 @@ pub struct Parameter {
     pub id: String,
     pub value: f64,
     pub constant: bool,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleKind {
+    Assignment,
+    Rate,
+}
+
+/// The variable an SBML rule sets. Rules are recorded but not evaluated,
+/// so the variable keeps its declared value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleTarget {
+    pub variable: String,
+    pub kind: RuleKind,
+}
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    
+    /// A model in one compartment of size 1. Reactions are
+    /// `(reactants, products, rate constant)` with species ids separated by
+    /// spaces, and are simulated by mass action.
+    pub(crate) fn mass_action(species: &[(&str, f64)], reactions: &[(&str, &str, f64)]) -> BioModelData {
+        let ids = |list: &str| list.split_whitespace().map(str::to_string).collect();
+        BioModelData {
+            compartments: vec![Compartment { id: "cell".to_string(), name: "cell".to_string(), size: 1.0 }],
+            species: species.iter()
+                .map(|&(id, initial)| Species {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    compartment: "cell".to_string(),
+                    initial_concentration: initial,
+                })
+                .collect(),
+            reactions: reactions.iter()
+                .enumerate()
+                .map(|(j, &(reactants, products, rate_constant))| Reaction {
+                    id: format!("R{}", j + 1),
+                    name: format!("R{}", j + 1),
+                    reactants: ids(reactants),
+                    products: ids(products),
+                    rate_constant,
+                    kinetic_law: String::new(),
+                    kinetic_math: None,
+                })
+                .collect(),
+            ..BioModelData::new()
+        }
+    }
+    
+    /// Gives reaction `j` the infix kinetic law `law` and declares
+    /// `parameters`.
+    pub(crate) fn with_law(mut model: BioModelData, j: usize, law: &str, parameters: &[(&str, f64)]) -> BioModelData {
+        model.parameters.extend(parameters.iter().map(|&(id, value)| Parameter {
+            id: id.to_string(),
+            value,
+            constant: true,
+        }));
+        model.reactions[j].kinetic_law = law.to_string();
+        model.reactions[j].kinetic_math = Some(crate::math::parse_infix(law).unwrap());
+        model
+    }
+}
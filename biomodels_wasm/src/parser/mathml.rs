@@ -0,0 +1,229 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::math::{Expr, Op};
+
+use super::ParserError;
+
+/// Minimal element tree for the contents of one `<math>` element.
+struct Node {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+    text: String,
+}
+
+impl Node {
+    fn new(e: &BytesStart) -> Result<Node, ParserError> {
+        let name = String::from_utf8(e.local_name().as_ref().to_vec())
+            .map_err(|e| ParserError::XmlError(e.to_string()))?;
+        let mut attributes = Vec::new();
+        for attr in e.attributes().flatten() {
+            let key = String::from_utf8(attr.key.local_name().as_ref().to_vec())
+                .map_err(|e| ParserError::XmlError(e.to_string()))?;
+            let value = attr.unescape_value()
+                .map_err(|e| ParserError::XmlError(e.to_string()))?
+                .to_string();
+            attributes.push((key, value));
+        }
+        Ok(Node { name, attributes, children: Vec::new(), text: String::new() })
+    }
+    
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Reads everything up to the `</math>` matching an already consumed
+/// `<math>` start tag. Text content is also appended to `text`, one token per
+/// text node, as the rest of the parser expects.
+///
+/// Returns `None` for MathML this evaluator does not support, such as calls
+/// to function definitions; the caller then keeps the mass-action fallback.
+pub fn parse_math(reader: &mut Reader<&[u8]>, text: &mut String) -> Result<Option<Expr>, ParserError> {
+    let mut stack = vec![Node {
+        name: "math".to_string(),
+        attributes: Vec::new(),
+        children: Vec::new(),
+        text: String::new(),
+    }];
+    let mut buf = Vec::new();
+    
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => stack.push(Node::new(&e)?),
+            Ok(Event::Empty(e)) => {
+                let node = Node::new(&e)?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(node);
+                }
+            }
+            Ok(Event::Text(e)) => {
+                let content = e.unescape()
+                    .map_err(|e| ParserError::XmlError(e.to_string()))?;
+                text.push_str(&content);
+                text.push(' ');
+                if let Some(node) = stack.last_mut() {
+                    node.text.push_str(&content);
+                    node.text.push(' ');
+                }
+            }
+            Ok(Event::End(_)) => {
+                let node = stack.pop()
+                    .ok_or_else(|| ParserError::InvalidStructure("Unbalanced MathML".to_string()))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => return Ok(node.children.first().and_then(to_expr)),
+                }
+            }
+            Ok(Event::Eof) => return Err(ParserError::InvalidStructure("Unterminated <math> element".to_string())),
+            Err(e) => return Err(ParserError::XmlError(e.to_string())),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Parses a standalone `<math>` element, such as an observable given as
+/// MathML rather than infix.
+pub fn parse_math_str(content: &str) -> Result<Expr, ParserError> {
+    let mut reader = Reader::from_str(content);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) if e.local_name().as_ref() == b"math" => break,
+            Ok(Event::Eof) => return Err(ParserError::InvalidStructure("No <math> element".to_string())),
+            Err(e) => return Err(ParserError::XmlError(e.to_string())),
+            _ => {}
+        }
+        buf.clear();
+    }
+    parse_math(&mut reader, &mut String::new())?
+        .ok_or_else(|| ParserError::InvalidStructure("Unsupported MathML construct".to_string()))
+}
+
+fn to_expr(node: &Node) -> Option<Expr> {
+    match node.name.as_str() {
+        "ci" => Some(Expr::Symbol(node.text.trim().to_string())),
+        "cn" => parse_number(node).map(Expr::Number),
+        "csymbol" => {
+            let url = node.attribute("definitionURL").unwrap_or("");
+            if url.ends_with("/time") {
+                Some(Expr::Time)
+            } else if url.ends_with("/avogadro") {
+                Some(Expr::Number(6.022_140_76e23))
+            } else {
+                None
+            }
+        }
+        "pi" => Some(Expr::Number(std::f64::consts::PI)),
+        "exponentiale" => Some(Expr::Number(std::f64::consts::E)),
+        "true" => Some(Expr::Number(1.0)),
+        "false" => Some(Expr::Number(0.0)),
+        "infinity" => Some(Expr::Number(f64::INFINITY)),
+        "notanumber" => Some(Expr::Number(f64::NAN)),
+        "apply" => apply_to_expr(node),
+        "piecewise" => {
+            let mut pieces = Vec::new();
+            let mut otherwise = None;
+            for child in &node.children {
+                match child.name.as_str() {
+                    "piece" => {
+                        let value = to_expr(child.children.first()?)?;
+                        let condition = to_expr(child.children.get(1)?)?;
+                        pieces.push((value, condition));
+                    }
+                    "otherwise" => otherwise = Some(Box::new(to_expr(child.children.first()?)?)),
+                    _ => return None,
+                }
+            }
+            Some(Expr::Piecewise(pieces, otherwise))
+        }
+        _ => None,
+    }
+}
+
+fn apply_to_expr(node: &Node) -> Option<Expr> {
+    let head = node.children.first()?;
+    let qualifier = |name: &str| {
+        node.children.iter()
+            .find(|c| c.name == name)
+            .and_then(|c| c.children.first())
+            .and_then(to_expr)
+    };
+    let args: Vec<Expr> = node.children[1..].iter()
+        .filter(|c| c.name != "degree" && c.name != "logbase")
+        .map(to_expr)
+        .collect::<Option<_>>()?;
+    
+    if head.name == "csymbol" {
+        let url = head.attribute("definitionURL").unwrap_or("");
+        if url.ends_with("/delay") && args.len() == 2 {
+            let mut args = args.into_iter();
+            let expr = args.next()?;
+            let tau = args.next()?;
+            return Some(Expr::Delay(Box::new(expr), Box::new(tau)));
+        }
+        return None;
+    }
+    
+    let op = match head.name.as_str() {
+        "plus" => Op::Plus,
+        "minus" => Op::Minus,
+        "times" => Op::Times,
+        "divide" => Op::Divide,
+        "power" => Op::Power,
+        "exp" => Op::Exp,
+        "ln" => Op::Ln,
+        "abs" => Op::Abs,
+        "floor" => Op::Floor,
+        "ceiling" => Op::Ceiling,
+        "min" => Op::Min,
+        "max" => Op::Max,
+        "lt" => Op::Lt,
+        "gt" => Op::Gt,
+        "leq" => Op::Leq,
+        "geq" => Op::Geq,
+        "eq" => Op::Eq,
+        "neq" => Op::Neq,
+        "and" => Op::And,
+        "or" => Op::Or,
+        "not" => Op::Not,
+        "root" => {
+            return match qualifier("degree") {
+                Some(degree) => Some(Expr::Apply(Op::Power, vec![
+                    args.into_iter().next()?,
+                    Expr::Apply(Op::Divide, vec![Expr::Number(1.0), degree]),
+                ])),
+                None => Some(Expr::Apply(Op::Sqrt, args)),
+            };
+        }
+        "log" => {
+            return match qualifier("logbase") {
+                Some(base) => Some(Expr::Apply(Op::Divide, vec![
+                    Expr::Apply(Op::Ln, args),
+                    Expr::Apply(Op::Ln, vec![base]),
+                ])),
+                None => Some(Expr::Apply(Op::Log10, args)),
+            };
+        }
+        _ => return None,
+    };
+    
+    Some(Expr::Apply(op, args))
+}
+
+fn parse_number(node: &Node) -> Option<f64> {
+    let parts: Vec<f64> = node.text.split_whitespace()
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    
+    match (node.attribute("type"), parts.as_slice()) {
+        (Some("e-notation"), [mantissa, exponent]) => Some(mantissa * 10f64.powf(*exponent)),
+        (Some("rational"), [numerator, denominator]) => Some(numerator / denominator),
+        (_, [value]) => Some(*value),
+        _ => None,
+    }
+}
@@ -1,9 +1,11 @@
 use quick_xml::events::Event;
 use quick_xml::Reader;
 use thiserror::Error;
-use wasm_bindgen::prelude::*;
 
-use crate::models::{BioModelData, Species, Reaction, Parameter, Compartment};
+use crate::models::{BioModelData, Species, Reaction, Parameter, Compartment, RuleKind, RuleTarget};
+use crate::utils::console_log;
+
+pub mod mathml;
 
 #[derive(Error, Debug)]
 pub enum ParserError {
@@ -21,11 +23,11 @@ pub fn parse_sbml(content: &str) -> Result<BioModelData, ParserError> {
     let mut buf = Vec::new();
     
     // Debug: Log content length
-    web_sys::console::log_1(&format!("Parsing SBML content of length: {}", content.len()).into());
+    console_log(&format!("Parsing SBML content of length: {}", content.len()));
     
     // Let's also log the first part of the content to see what we're actually parsing
     let preview = if content.len() > 500 { &content[0..500] } else { content };
-    web_sys::console::log_1(&format!("Content preview: {}", preview).into());
+    console_log(&format!("Content preview: {}", preview));
     
     loop {
         match reader.read_event_into(&mut buf) {
@@ -34,46 +36,62 @@ pub fn parse_sbml(content: &str) -> Result<BioModelData, ParserError> {
                     .map_err(|e| ParserError::XmlError(e.to_string()))?;
                 
                 // Debug: log all tags we encounter
-                web_sys::console::log_1(&format!("Start/Empty Tag: '{}', Section: '{}'", name, current_section).into());
+                console_log(&format!("Start/Empty Tag: '{}', Section: '{}'", name, current_section));
                 
                 match name.as_str() {
+                    "model" => {
+                        model_data.id = e.attributes().flatten()
+                            .find(|attr| attr.key.as_ref() == b"id")
+                            .and_then(|attr| attr.unescape_value().ok())
+                            .map(|id| id.to_string());
+                    }
                     "listOfCompartments" => {
                         current_section = "compartments".to_string();
-                        web_sys::console::log_1(&"Setting section to compartments".into());
+                        console_log("Setting section to compartments");
                     }
                     "listOfSpecies" => {
                         current_section = "species".to_string();
-                        web_sys::console::log_1(&"Setting section to species".into());
+                        console_log("Setting section to species");
                     }
                     "listOfParameters" => {
                         current_section = "parameters".to_string();
-                        web_sys::console::log_1(&"Setting section to parameters".into());
+                        console_log("Setting section to parameters");
                     }
                     "listOfReactions" => {
                         current_section = "reactions".to_string();
-                        web_sys::console::log_1(&"Setting section to reactions".into());
+                        console_log("Setting section to reactions");
                     }
                     "compartment" if current_section == "compartments" => {
-                        web_sys::console::log_1(&"Parsing compartment".into());
+                        console_log("Parsing compartment");
                         if let Some(comp) = parse_compartment(&e) {
                             model_data.compartments.push(comp);
                         }
                     }
                     "species" if current_section == "species" => {
-                        web_sys::console::log_1(&"Parsing species".into());
+                        console_log("Parsing species");
                         if let Some(spec) = parse_species(&e) {
-                            web_sys::console::log_1(&format!("Found species: {} ({})", spec.name, spec.id).into());
+                            console_log(&format!("Found species: {} ({})", spec.name, spec.id));
                             model_data.species.push(spec);
                         }
                     }
                     "parameter" if current_section == "parameters" => {
-                        web_sys::console::log_1(&"Parsing parameter".into());
+                        console_log("Parsing parameter");
                         if let Some(param) = parse_parameter(&e) {
                             model_data.parameters.push(param);
                         }
                     }
+                    "assignmentRule" | "rateRule" => {
+                        let kind = if name == "rateRule" { RuleKind::Rate } else { RuleKind::Assignment };
+                        let variable = e.attributes().flatten()
+                            .find(|attr| attr.key.as_ref() == b"variable")
+                            .and_then(|attr| attr.unescape_value().ok())
+                            .map(|variable| variable.to_string());
+                        if let Some(variable) = variable {
+                            model_data.rules.push(RuleTarget { variable, kind });
+                        }
+                    }
                     "reaction" if current_section == "reactions" => {
-                        web_sys::console::log_1(&"Parsing reaction".into());
+                        console_log("Parsing reaction");
                         if let Some(reaction) = parse_reaction(&e, &mut reader)? {
                             model_data.reactions.push(reaction);
                         }
@@ -86,7 +104,7 @@ pub fn parse_sbml(content: &str) -> Result<BioModelData, ParserError> {
                     .map_err(|e| ParserError::XmlError(e.to_string()))?;
                 
                 if name.starts_with("listOf") {
-                    web_sys::console::log_1(&format!("Clearing section on end of: {}", name).into());
+                    console_log(&format!("Clearing section on end of: {}", name));
                     current_section.clear();
                 }
             }
@@ -97,8 +115,8 @@ pub fn parse_sbml(content: &str) -> Result<BioModelData, ParserError> {
         buf.clear();
     }
     
-    web_sys::console::log_1(&format!("Parsing complete: {} species, {} parameters, {} reactions", 
-        model_data.species.len(), model_data.parameters.len(), model_data.reactions.len()).into());
+    console_log(&format!("Parsing complete: {} species, {} parameters, {} reactions", 
+        model_data.species.len(), model_data.parameters.len(), model_data.reactions.len()));
     
     Ok(model_data)
 }
@@ -106,22 +124,22 @@ pub fn parse_sbml(content: &str) -> Result<BioModelData, ParserError> {
 fn parse_compartment(e: &quick_xml::events::BytesStart) -> Option<Compartment> {
     let mut id = String::new();
     let mut name = String::new();
+    let mut size = 1.0;
     
-    for attr in e.attributes() {
-        if let Ok(attr) = attr {
-            let key = std::str::from_utf8(attr.key.as_ref()).ok()?;
-            let value = attr.unescape_value().ok()?;
-            
-            match key {
-                "id" => id = value.to_string(),
-                "name" => name = value.to_string(),
-                _ => {}
-            }
+    for attr in e.attributes().flatten() {
+        let key = std::str::from_utf8(attr.key.as_ref()).ok()?;
+        let value = attr.unescape_value().ok()?;
+        
+        match key {
+            "id" => id = value.to_string(),
+            "name" => name = value.to_string(),
+            "size" => size = value.parse().unwrap_or(1.0),
+            _ => {}
         }
     }
     
     if !id.is_empty() {
-        Some(Compartment { id, name })
+        Some(Compartment { id, name, size })
     } else {
         None
     }
@@ -133,23 +151,21 @@ fn parse_species(e: &quick_xml::events::BytesStart) -> Option<Species> {
     let mut compartment = String::new();
     let mut initial_amount = 0.0;
     
-    web_sys::console::log_1(&format!("Parsing species with {} attributes", e.attributes().count()).into());
+    console_log(&format!("Parsing species with {} attributes", e.attributes().count()));
     
-    for attr in e.attributes() {
-        if let Ok(attr) = attr {
-            let key = std::str::from_utf8(attr.key.as_ref()).ok()?;
-            let value = attr.unescape_value().ok()?;
-            
-            web_sys::console::log_1(&format!("  Species attr: {} = {}", key, value).into());
-            
-            match key {
-                "id" => id = value.to_string(),
-                "name" => name = value.to_string(),
-                "compartment" => compartment = value.to_string(),
-                "initialAmount" => initial_amount = value.parse().unwrap_or(0.0),
-                "initialConcentration" => initial_amount = value.parse().unwrap_or(0.0),
-                _ => {}
-            }
+    for attr in e.attributes().flatten() {
+        let key = std::str::from_utf8(attr.key.as_ref()).ok()?;
+        let value = attr.unescape_value().ok()?;
+        
+        console_log(&format!("  Species attr: {} = {}", key, value));
+        
+        match key {
+            "id" => id = value.to_string(),
+            "name" => name = value.to_string(),
+            "compartment" => compartment = value.to_string(),
+            "initialAmount" => initial_amount = value.parse().unwrap_or(0.0),
+            "initialConcentration" => initial_amount = value.parse().unwrap_or(0.0),
+            _ => {}
         }
     }
     
@@ -171,17 +187,15 @@ fn parse_parameter(e: &quick_xml::events::BytesStart) -> Option<Parameter> {
     let mut value = 0.0;
     let mut constant = true;
     
-    for attr in e.attributes() {
-        if let Ok(attr) = attr {
-            let key = std::str::from_utf8(attr.key.as_ref()).ok()?;
-            let attr_value = attr.unescape_value().ok()?;
-            
-            match key {
-                "id" => id = attr_value.to_string(),
-                "value" => value = attr_value.parse().unwrap_or(0.0),
-                "constant" => constant = attr_value == "true",
-                _ => {}
-            }
+    for attr in e.attributes().flatten() {
+        let key = std::str::from_utf8(attr.key.as_ref()).ok()?;
+        let attr_value = attr.unescape_value().ok()?;
+        
+        match key {
+            "id" => id = attr_value.to_string(),
+            "value" => value = attr_value.parse().unwrap_or(0.0),
+            "constant" => constant = attr_value == "true",
+            _ => {}
         }
     }
     
@@ -199,24 +213,23 @@ fn parse_reaction(
     let mut id = String::new();
     let mut name = String::new();
     
-    for attr in e.attributes() {
-        if let Ok(attr) = attr {
-            let key = std::str::from_utf8(attr.key.as_ref())
-                .map_err(|e| ParserError::XmlError(e.to_string()))?;
-            let value = attr.unescape_value()
-                .map_err(|e| ParserError::XmlError(e.to_string()))?;
-            
-            match key {
-                "id" => id = value.to_string(),
-                "name" => name = value.to_string(),
-                _ => {}
-            }
+    for attr in e.attributes().flatten() {
+        let key = std::str::from_utf8(attr.key.as_ref())
+            .map_err(|e| ParserError::XmlError(e.to_string()))?;
+        let value = attr.unescape_value()
+            .map_err(|e| ParserError::XmlError(e.to_string()))?;
+        
+        match key {
+            "id" => id = value.to_string(),
+            "name" => name = value.to_string(),
+            _ => {}
         }
     }
     
     let mut reactants = Vec::new();
     let mut products = Vec::new();
     let mut kinetic_law = String::new();
+    let mut kinetic_math = None;
     let mut in_reactants = false;
     let mut in_products = false;
     let mut in_kinetic_law = false;
@@ -233,28 +246,18 @@ fn parse_reaction(
                     "listOfProducts" => in_products = true,
                     "kineticLaw" => in_kinetic_law = true,
                     "speciesReference" => {
-                        for attr in e.attributes() {
-                            if let Ok(attr) = attr {
-                                let key = std::str::from_utf8(attr.key.as_ref())
-                                    .map_err(|e| ParserError::XmlError(e.to_string()))?;
-                                
-                                if key == "species" {
-                                    let species_id = attr.unescape_value()
-                                        .map_err(|e| ParserError::XmlError(e.to_string()))?
-                                        .to_string();
-                                    
-                                    if in_reactants {
-                                        reactants.push(species_id);
-                                    } else if in_products {
-                                        products.push(species_id);
-                                    }
-                                }
-                            }
-                        }
+                        add_species_reference(&e, in_reactants, in_products, &mut reactants, &mut products)?;
+                    }
+                    "math" if in_kinetic_law => {
+                        kinetic_math = mathml::parse_math(reader, &mut kinetic_law)?;
                     }
                     _ => {}
                 }
             }
+            // Self-closing <speciesReference species="..."/> is the usual form
+            Ok(Event::Empty(e)) if e.name().as_ref() == b"speciesReference" => {
+                add_species_reference(&e, in_reactants, in_products, &mut reactants, &mut products)?;
+            }
             Ok(Event::End(e)) => {
                 let tag_name = String::from_utf8(e.name().as_ref().to_vec())
                     .map_err(|e| ParserError::XmlError(e.to_string()))?;
@@ -270,6 +273,8 @@ fn parse_reaction(
             Ok(Event::Text(e)) if in_kinetic_law => {
                 kinetic_law.push_str(&e.unescape()
                     .map_err(|e| ParserError::XmlError(e.to_string()))?);
+                // Keep adjacent <ci> names apart so the law can be tokenised
+                kinetic_law.push(' ');
             }
             Ok(Event::Eof) => break,
             Err(e) => return Err(ParserError::XmlError(e.to_string())),
@@ -287,8 +292,99 @@ fn parse_reaction(
             products,
             rate_constant: 0.1,
             kinetic_law,
+            kinetic_math,
         }))
     } else {
         Ok(None)
     }
-}
\ No newline at end of file
+}
+
+/// Adds the species of a `speciesReference` to the reactants or products,
+/// whichever list it sits in, once per copy.
+fn add_species_reference(
+    e: &quick_xml::events::BytesStart,
+    in_reactants: bool,
+    in_products: bool,
+    reactants: &mut Vec<String>,
+    products: &mut Vec<String>,
+) -> Result<(), ParserError> {
+    if let Some((species_id, copies)) = species_reference(e)? {
+        if in_reactants {
+            reactants.extend(std::iter::repeat_n(species_id, copies));
+        } else if in_products {
+            products.extend(std::iter::repeat_n(species_id, copies));
+        }
+    }
+    Ok(())
+}
+
+/// Species and stoichiometry of a `speciesReference`. Reactions list a
+/// species once per copy, so the stoichiometry must be a positive whole
+/// number; it defaults to 1.
+fn species_reference(e: &quick_xml::events::BytesStart) -> Result<Option<(String, usize)>, ParserError> {
+    let mut species_id = None;
+    let mut copies = 1;
+    
+    for attr in e.attributes().flatten() {
+        let key = std::str::from_utf8(attr.key.as_ref())
+            .map_err(|e| ParserError::XmlError(e.to_string()))?;
+        let value = attr.unescape_value()
+            .map_err(|e| ParserError::XmlError(e.to_string()))?;
+        
+        match key {
+            "species" => species_id = Some(value.to_string()),
+            "stoichiometry" => {
+                let stoichiometry: f64 = value.trim().parse()
+                    .map_err(|_| ParserError::InvalidStructure(format!("Invalid stoichiometry '{}'", value)))?;
+                if stoichiometry < 1.0 || stoichiometry.fract() != 0.0 {
+                    return Err(ParserError::InvalidStructure(format!(
+                        "Stoichiometry {} is not a positive whole number", stoichiometry
+                    )));
+                }
+                copies = stoichiometry as usize;
+            }
+            _ => {}
+        }
+    }
+    Ok(species_id.map(|id| (id, copies)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const SBML: &str = r#"<sbml><model id="growth">
+        <listOfCompartments><compartment id="cell" size="2"/></listOfCompartments>
+        <listOfSpecies><species id="OPC" compartment="cell" initialConcentration="1"/></listOfSpecies>
+        <listOfParameters><parameter id="week" value="10" constant="false"/></listOfParameters>
+        <listOfRules>
+            <rateRule variable="week"><math><cn>1</cn></math></rateRule>
+        </listOfRules>
+        <listOfReactions>
+            <reaction id="divide">
+                <listOfReactants><speciesReference species="OPC"/></listOfReactants>
+                <listOfProducts><speciesReference species="OPC" stoichiometry="2"/></listOfProducts>
+            </reaction>
+        </listOfReactions>
+    </model></sbml>"#;
+    
+    #[test]
+    fn repeats_species_by_stoichiometry() {
+        let model = parse_sbml(SBML).unwrap();
+        assert_eq!(model.reactions[0].reactants, ["OPC"]);
+        assert_eq!(model.reactions[0].products, ["OPC", "OPC"]);
+    }
+    
+    #[test]
+    fn rejects_fractional_stoichiometry() {
+        let sbml = SBML.replace(r#"stoichiometry="2""#, r#"stoichiometry="1.5""#);
+        assert!(matches!(parse_sbml(&sbml), Err(ParserError::InvalidStructure(_))));
+    }
+    
+    #[test]
+    fn records_rule_targets() {
+        let model = parse_sbml(SBML).unwrap();
+        assert_eq!(model.rules.len(), 1);
+        assert_eq!((model.rules[0].variable.as_str(), model.rules[0].kind), ("week", RuleKind::Rate));
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use nalgebra::DVector;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+use wasm_bindgen::prelude::*;
+
+use super::{Simulator, Positivity};
+use super::dosing::DosingSchedule;
+use super::forcing::Forcing;
+use super::hybrid::HybridOptions;
+use super::observables::Observable;
+use super::schedule::ParameterSchedule;
+
+/// Bumped whenever fields are added to `SimulatorState`. Older checkpoints
+/// still load, with the missing fields at their defaults.
+pub const CHECKPOINT_VERSION: u32 = 3;
+
+/// The run options in force when a checkpoint was taken, so a restored
+/// simulator steps exactly as the saved one would have.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSettings {
+    pub seed: Option<u64>,
+    pub positivity: String,
+    pub divergence_threshold: f64,
+    pub hybrid: HybridOptions,
+    pub stochastic_scale: f64,
+    pub reduce_conservation: bool,
+    pub record_fluxes: bool,
+    pub observables: Vec<Observable>,
+}
+
+/// Everything needed to continue a run exactly where it stopped.
+///
+/// The simulator has no SBML event support yet, so there are no pending
+/// delayed events or trigger history to capture. Models with delayed kinetic
+/// laws also carry their state history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulatorState {
+    pub version: u32,
+    pub time: f64,
+    pub species_ids: Vec<String>,
+    pub state: Vec<f64>,
+    pub rate_constants: Vec<f64>,
+    pub parameters: BTreeMap<String, f64>,
+    /// Generator position for the stochastic methods; absent in
+    /// checkpoints written before SSA support.
+    #[serde(default)]
+    pub rng: Option<ChaCha8Rng>,
+    /// Past states for models with delayed kinetic laws.
+    #[serde(default)]
+    pub history: Vec<(f64, Vec<f64>)>,
+    /// Dosing schedules in force, so doses after `time` are still given.
+    #[serde(default)]
+    pub dosing: Vec<DosingSchedule>,
+    /// Time series bound to species or parameters.
+    #[serde(default)]
+    pub forcings: Vec<Forcing>,
+    #[serde(default)]
+    pub parameter_schedules: Vec<ParameterSchedule>,
+    /// Conserved totals of a reduced run, one per conservation law, so a
+    /// continued run enforces the same totals rather than recomputing them
+    /// from the rounded state.
+    #[serde(default)]
+    pub conservation_totals: Option<Vec<f64>>,
+    /// Absent in checkpoints written before version 3, which leave the
+    /// simulator's current options alone.
+    #[serde(default)]
+    pub settings: Option<RunSettings>,
+}
+
+impl SimulatorState {
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        serde_json::to_string(self)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize state: {}", e)))
+    }
+    
+    pub fn from_json(blob: &str) -> Result<SimulatorState, JsValue> {
+        let saved: SimulatorState = serde_json::from_str(blob)
+            .map_err(|e| JsValue::from_str(&format!("Invalid saved state: {}", e)))?;
+        
+        if saved.version == 0 || saved.version > CHECKPOINT_VERSION {
+            return Err(JsValue::from_str(&format!(
+                "Unsupported saved state version {} (expected at most {})",
+                saved.version, CHECKPOINT_VERSION
+            )));
+        }
+        
+        Ok(saved)
+    }
+}
+
+impl Simulator {
+    pub fn save_state(&self) -> SimulatorState {
+        SimulatorState {
+            version: CHECKPOINT_VERSION,
+            time: self.time,
+            species_ids: self.model_ref.species.iter().map(|s| s.id.clone()).collect(),
+            state: self.state.as_slice().to_vec(),
+            rate_constants: self.rate_constants.clone(),
+            parameters: self.model_ref.parameters.iter()
+                .filter(|p| !p.constant)
+                .map(|p| (p.id.clone(), p.value))
+                .collect(),
+            rng: Some(self.rng.clone()),
+            history: self.history.iter()
+                .map(|(t, x)| (*t, x.as_slice().to_vec()))
+                .collect(),
+            dosing: self.dosing.clone(),
+            forcings: self.forcings(),
+            parameter_schedules: self.parameter_schedules.clone(),
+            conservation_totals: self.reduction.as_ref().map(|r| r.totals()),
+            settings: Some(RunSettings {
+                seed: self.seed,
+                positivity: self.positivity.name().to_string(),
+                divergence_threshold: self.divergence_threshold,
+                hybrid: self.hybrid.clone(),
+                stochastic_scale: self.stochastic_scale,
+                reduce_conservation: self.reduce_conservation,
+                record_fluxes: self.record_fluxes,
+                observables: self.observables.iter().map(|o| o.observable.clone()).collect(),
+            }),
+        }
+    }
+    
+    pub fn load_state(&mut self, saved: &SimulatorState) -> Result<(), JsValue> {
+        let species_ids: Vec<&str> = self.model_ref.species.iter().map(|s| s.id.as_str()).collect();
+        if saved.species_ids != species_ids || saved.state.len() != species_ids.len() {
+            return Err(JsValue::from_str("Saved state does not match the loaded model's species"));
+        }
+        if saved.rate_constants.len() != self.rate_constants.len() {
+            return Err(JsValue::from_str("Saved state does not match the loaded model's reactions"));
+        }
+        if saved.history.iter().any(|(_, x)| x.len() != species_ids.len()) {
+            return Err(JsValue::from_str("Saved history does not match the loaded model's species"));
+        }
+        if let Some(schedule) = saved.dosing.iter().find(|s| !species_ids.contains(&s.target.as_str())) {
+            return Err(JsValue::from_str(&format!("Saved dosing target '{}' not found", schedule.target)));
+        }
+        let forcings = saved.forcings.iter()
+            .map(|f| self.resolve_forcing(f.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| JsValue::from_str(&e))?;
+        for schedule in &saved.parameter_schedules {
+            self.validate_schedule(schedule)
+                .map_err(|e| JsValue::from_str(&e))?;
+        }
+        
+        let reduction = match &saved.conservation_totals {
+            Some(totals) => Some(self.reduction_with_totals(totals)
+                .ok_or_else(|| JsValue::from_str("Saved conservation totals do not match the loaded model's conservation laws"))?),
+            None => None,
+        };
+        
+        let positivity = saved.settings.as_ref()
+            .map(|s| Positivity::parse(&s.positivity))
+            .transpose()
+            .map_err(|e| JsValue::from_str(&e))?;
+        if let Some(settings) = &saved.settings {
+            self.set_hybrid_options(settings.hybrid.clone())
+                .and_then(|()| self.set_stochastic_scale(settings.stochastic_scale))
+                .and_then(|()| self.set_observables(settings.observables.clone()))
+                .map_err(|e| JsValue::from_str(&e))?;
+            self.seed = settings.seed;
+            self.divergence_threshold = settings.divergence_threshold;
+            self.reduce_conservation = settings.reduce_conservation;
+            self.record_fluxes = settings.record_fluxes;
+        }
+        if let Some(positivity) = positivity {
+            self.positivity = positivity;
+        }
+        
+        for (id, value) in &saved.parameters {
+            let param = self.model_ref.parameters.iter_mut()
+                .find(|p| &p.id == id)
+                .ok_or_else(|| JsValue::from_str(&format!("Parameter '{}' not found", id)))?;
+            param.value = *value;
+        }
+        
+        self.time = saved.time;
+        self.events.clear();
+        self.state.copy_from_slice(&saved.state);
+        self.rate_constants.clone_from(&saved.rate_constants);
+        if let Some(rng) = &saved.rng {
+            self.rng = rng.clone();
+        }
+        self.history = saved.history.iter()
+            .map(|(t, x)| (*t, DVector::from_column_slice(x)))
+            .collect();
+        self.dosing.clone_from(&saved.dosing);
+        self.forcings = forcings;
+        self.parameter_schedules.clone_from(&saved.parameter_schedules);
+        self.reduction = reduction;
+        
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing::mass_action;
+    use crate::simulator::DoseKind;
+    
+    #[test]
+    fn continuing_from_a_checkpoint_is_bit_identical() {
+        let model = mass_action(&[("A", 50.0), ("B", 0.0)], &[("A", "B", 0.4), ("B", "A", 0.1)]);
+        let mut original = Simulator::new(&model);
+        original.set_seed(Some(7));
+        // A dose after the checkpoint must survive the round trip.
+        original.add_dosing_schedule(DosingSchedule {
+            target: "A".to_string(),
+            amount: 20.0,
+            start: 7.5,
+            interval: 0.0,
+            count: 1,
+            kind: DoseKind::Bolus,
+            duration: None,
+        }).unwrap();
+        original.simulate(5.0, 0.5, "ssa").unwrap();
+        
+        let blob = original.save_state().to_json().unwrap();
+        let mut resumed = Simulator::new(&model);
+        resumed.load_state(&SimulatorState::from_json(&blob).unwrap()).unwrap();
+        
+        let expected = original.advance(10.0, 0.5, "ssa").unwrap();
+        let continued = resumed.advance(10.0, 0.5, "ssa").unwrap();
+        assert_eq!(continued.time, expected.time);
+        assert_eq!(continued.values, expected.values);
+    }
+    
+    #[test]
+    fn a_checkpoint_carries_the_run_options() {
+        let model = mass_action(&[("A", 50.0), ("B", 0.0)], &[("A", "B", 0.4), ("B", "A", 0.1)]);
+        let mut original = Simulator::new(&model);
+        original.set_seed(Some(11));
+        original.set_positivity(Positivity::Reject);
+        original.set_stochastic_scale(20.0).unwrap();
+        original.set_hybrid_options(HybridOptions { fast_reactions: vec!["R1".to_string()], threshold: 5.0 }).unwrap();
+        original.set_record_fluxes(true);
+        original.set_observables(vec![Observable { id: "total".to_string(), expression: "A + B".to_string() }]).unwrap();
+        original.simulate(5.0, 0.5, "hybrid").unwrap();
+        
+        // The restoring simulator is left at its defaults.
+        let blob = original.save_state().to_json().unwrap();
+        let mut resumed = Simulator::new(&model);
+        resumed.load_state(&SimulatorState::from_json(&blob).unwrap()).unwrap();
+        
+        let expected = original.advance(10.0, 0.5, "hybrid").unwrap();
+        let continued = resumed.advance(10.0, 0.5, "hybrid").unwrap();
+        assert_eq!(continued.values, expected.values);
+        // Trajectories and fluxes have no PartialEq; compare their JSON.
+        assert_eq!(serde_json::to_string(&continued.variables).unwrap(), serde_json::to_string(&expected.variables).unwrap());
+        assert_eq!(serde_json::to_string(&continued.fluxes).unwrap(), serde_json::to_string(&expected.fluxes).unwrap());
+        assert_eq!(continued.run_info.unwrap().stochastic_scale, Some(20.0));
+    }
+}
@@ -0,0 +1,327 @@
+use nalgebra::{DMatrix, DVector};
+use serde::{Serialize, Deserialize};
+
+use crate::models::BioModelData;
+
+use super::Simulator;
+
+/// Coefficients below this are treated as zero after row reduction.
+pub(crate) const COEFFICIENT_TOLERANCE: f64 = 1e-9;
+
+/// A conserved moiety: `Σ coefficient · species` stays equal to `total`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConservationLaw {
+    /// Species eliminated when simulating the reduced system; its
+    /// coefficient is always 1.
+    pub dependent_species: String,
+    pub coefficients: Vec<(String, f64)>,
+    /// Value of the conserved sum: for the initial concentrations from
+    /// `conservation_laws`, or the total a reduced run enforced.
+    pub total: f64,
+}
+
+/// Dependent species and how to rebuild them from the independent ones.
+#[derive(Debug, Clone)]
+pub(crate) struct Reduction {
+    laws: Vec<ReducedLaw>,
+    /// Species in no law or in a law without being its dependent, in
+    /// species order: the variables of the reduced system.
+    independent: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct ReducedLaw {
+    dependent: usize,
+    /// (independent species index, coefficient)
+    others: Vec<(usize, f64)>,
+    total: f64,
+}
+
+impl Simulator {
+    /// Orthonormal basis of the left null space of the stoichiometry matrix,
+    /// one column per independent conserved combination of species.
+    pub fn left_null_space(&self) -> DMatrix<f64> {
+        let n = self.stoichiometry_matrix.nrows();
+        let gram = &self.stoichiometry_matrix * self.stoichiometry_matrix.transpose();
+        let eigen = gram.symmetric_eigen();
+        let scale = eigen.eigenvalues.iter().fold(1.0_f64, |acc, &x| acc.max(x.abs()));
+        
+        let columns: Vec<DVector<f64>> = (0..n)
+            .filter(|&i| eigen.eigenvalues[i].abs() <= 1e-10 * scale)
+            .map(|i| eigen.eigenvectors.column(i).into_owned())
+            .collect();
+        
+        if columns.is_empty() {
+            DMatrix::zeros(n, 0)
+        } else {
+            DMatrix::from_columns(&columns)
+        }
+    }
+    
+    /// Conservation laws in reduced row echelon form, so each law has one
+    /// dependent species that appears in no other law.
+    pub fn conservation_laws(&self) -> Vec<ConservationLaw> {
+        let initial = DVector::from_iterator(
+            self.state.len(),
+            self.model_ref.species.iter().map(|s| s.initial_concentration),
+        );
+        
+        self.echelon_laws().into_iter()
+            .map(|(pivot, row)| ConservationLaw {
+                dependent_species: self.model_ref.species[pivot].id.clone(),
+                coefficients: row.iter()
+                    .enumerate()
+                    .filter(|(_, c)| **c != 0.0)
+                    .map(|(i, c)| (self.model_ref.species[i].id.clone(), *c))
+                    .collect(),
+                total: row.dot(&initial),
+            })
+            .collect()
+    }
+    
+    /// Turns reduced integration on or off. The conserved totals are taken
+    /// from the state at the start of each run, and kept when a run is
+    /// continued.
+    ///
+    /// The deterministic integrators then step only the independent
+    /// species. Each stage rebuilds the dependent species from the totals
+    /// to evaluate the rates, and so does every accepted step, so the
+    /// totals hold exactly.
+    pub fn set_conservation_reduction(&mut self, enabled: bool) {
+        self.reduce_conservation = enabled;
+    }
+    
+    pub(crate) fn build_reduction(&self) -> Reduction {
+        let laws = self.echelon_laws().into_iter()
+            .map(|(pivot, row)| {
+                let others = row.iter()
+                    .enumerate()
+                    .filter(|(i, c)| *i != pivot && **c != 0.0)
+                    .map(|(i, c)| (i, *c))
+                    .collect();
+                ReducedLaw {
+                    dependent: pivot,
+                    others,
+                    total: row.dot(&self.state),
+                }
+            })
+            .collect::<Vec<ReducedLaw>>();
+        let independent = (0..self.state.len())
+            .filter(|&i| !laws.iter().any(|law| law.dependent == i))
+            .collect();
+        Reduction { laws, independent }
+    }
+    
+    /// A reduction enforcing `totals`, one per law, or `None` when the model
+    /// has a different number of laws.
+    pub(crate) fn reduction_with_totals(&self, totals: &[f64]) -> Option<Reduction> {
+        let mut reduction = self.build_reduction();
+        if reduction.laws.len() != totals.len() {
+            return None;
+        }
+        for (law, total) in reduction.laws.iter_mut().zip(totals) {
+            law.total = *total;
+        }
+        Some(reduction)
+    }
+    
+    /// The variables the deterministic integrators step: the independent
+    /// species when reducing, otherwise the whole state.
+    pub(crate) fn integration_state(&self) -> DVector<f64> {
+        match &self.reduction {
+            Some(reduction) => reduction.independent_part(&self.state),
+            None => self.state.clone(),
+        }
+    }
+    
+    /// Sets the state from integration variables, rebuilding the dependent
+    /// species when reducing.
+    pub(crate) fn set_integration_state(&mut self, y: DVector<f64>) {
+        self.state = match &self.reduction {
+            Some(reduction) => reduction.expand(&y),
+            None => y,
+        };
+    }
+    
+    /// Right-hand side for the integration variables `y`: the derivatives
+    /// of the full state, or of the independent species with the dependent
+    /// ones rebuilt from the totals, plus any infusion input.
+    pub(crate) fn integration_derivatives(&self, t: f64, y: &DVector<f64>) -> DVector<f64> {
+        match &self.reduction {
+            Some(reduction) => {
+                let mut derivatives = self.compute_derivatives_at(t, &reduction.expand(y));
+                if let Some(input) = &self.input {
+                    derivatives += input;
+                }
+                reduction.independent_part(&derivatives)
+            }
+            None => {
+                let mut derivatives = self.compute_derivatives_at(t, y);
+                if let Some(input) = &self.input {
+                    derivatives += input;
+                }
+                derivatives
+            }
+        }
+    }
+    
+    fn echelon_laws(&self) -> Vec<(usize, DVector<f64>)> {
+        let mut rows = self.left_null_space().transpose();
+        let (m, n) = rows.shape();
+        let mut pivots = Vec::new();
+        let mut r = 0;
+        
+        for col in 0..n {
+            if r == m {
+                break;
+            }
+            let best = (r..m).max_by(|&a, &b| rows[(a, col)].abs().total_cmp(&rows[(b, col)].abs()));
+            let best = match best {
+                Some(best) if rows[(best, col)].abs() > COEFFICIENT_TOLERANCE => best,
+                _ => continue,
+            };
+            rows.swap_rows(r, best);
+            
+            let pivot = rows[(r, col)];
+            let pivot_row = rows.row(r) / pivot;
+            rows.set_row(r, &pivot_row);
+            for other in 0..m {
+                if other != r {
+                    let factor = rows[(other, col)];
+                    let updated = rows.row(other) - &pivot_row * factor;
+                    rows.set_row(other, &updated);
+                }
+            }
+            pivots.push(col);
+            r += 1;
+        }
+        
+        pivots.into_iter()
+            .enumerate()
+            .map(|(i, pivot)| {
+                let row = rows.row(i).transpose().map(|c| {
+                    let rounded = c.round();
+                    if c.abs() < COEFFICIENT_TOLERANCE {
+                        0.0
+                    } else if (c - rounded).abs() < COEFFICIENT_TOLERANCE {
+                        rounded
+                    } else {
+                        c
+                    }
+                });
+                (pivot, row)
+            })
+            .collect()
+    }
+}
+
+impl Reduction {
+    pub(crate) fn totals(&self) -> Vec<f64> {
+        self.laws.iter().map(|law| law.total).collect()
+    }
+    
+    /// The laws with the totals this reduction enforces.
+    pub(crate) fn conservation_laws(&self, model: &BioModelData) -> Vec<ConservationLaw> {
+        self.laws.iter()
+            .map(|law| {
+                let mut terms: Vec<(usize, f64)> = law.others.clone();
+                terms.push((law.dependent, 1.0));
+                terms.sort_by_key(|(i, _)| *i);
+                ConservationLaw {
+                    dependent_species: model.species[law.dependent].id.clone(),
+                    coefficients: terms.into_iter()
+                        .map(|(i, c)| (model.species[i].id.clone(), c))
+                        .collect(),
+                    total: law.total,
+                }
+            })
+            .collect()
+    }
+    
+    /// Whether species `i` appears in any of the laws.
+    pub(crate) fn involves(&self, i: usize) -> bool {
+        self.laws.iter()
+            .any(|law| law.dependent == i || law.others.iter().any(|(j, _)| *j == i))
+    }
+    
+    fn independent_part(&self, full: &DVector<f64>) -> DVector<f64> {
+        DVector::from_iterator(self.independent.len(), self.independent.iter().map(|&i| full[i]))
+    }
+    
+    /// The full state for independent species `y`.
+    fn expand(&self, y: &DVector<f64>) -> DVector<f64> {
+        let n = self.independent.len() + self.laws.len();
+        let mut state = DVector::zeros(n);
+        for (&i, &value) in self.independent.iter().zip(y.iter()) {
+            state[i] = value;
+        }
+        for law in &self.laws {
+            state[law.dependent] = law.total - law.others.iter().map(|(i, c)| c * state[*i]).sum::<f64>();
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing::mass_action;
+    use crate::simulator::Positivity;
+    
+    fn enzyme() -> BioModelData {
+        mass_action(
+            &[("E", 1.0), ("S", 10.0), ("ES", 0.0), ("P", 0.0)],
+            &[("E S", "ES", 1.0), ("ES", "E S", 0.5), ("ES", "E P", 0.3)],
+        )
+    }
+    
+    fn coefficients(law: &ConservationLaw) -> Vec<(&str, f64)> {
+        law.coefficients.iter()
+            .map(|(id, c)| (id.as_str(), (c * 1e6).round() / 1e6))
+            .collect()
+    }
+    
+    #[test]
+    fn detects_the_enzyme_and_substrate_moieties() {
+        let laws = Simulator::new(&enzyme()).conservation_laws();
+        assert_eq!(laws.len(), 2);
+        
+        assert_eq!(laws[0].dependent_species, "E");
+        assert_eq!(coefficients(&laws[0]), [("E", 1.0), ("ES", 1.0)]);
+        assert!((laws[0].total - 1.0).abs() < 1e-9);
+        assert_eq!(laws[1].dependent_species, "S");
+        assert_eq!(coefficients(&laws[1]), [("S", 1.0), ("ES", 1.0), ("P", 1.0)]);
+        assert!((laws[1].total - 10.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn reduced_runs_keep_the_totals() {
+        let mut sim = Simulator::new(&enzyme());
+        sim.set_conservation_reduction(true);
+        // RK4 overshoots E below zero at this step; clipping it would break
+        // the totals, while rejecting the step keeps them.
+        sim.set_positivity(Positivity::Reject);
+        let results = sim.simulate(20.0, 0.5, "rk4").unwrap();
+        assert_eq!(results.conservation_laws.map(|laws| laws.len()), Some(2));
+        for row in results.values.chunks(4) {
+            assert!((row[0] + row[2] - 1.0).abs() < 1e-12);
+            assert!((row[1] + row[2] + row[3] - 10.0).abs() < 1e-12);
+        }
+    }
+    
+    #[test]
+    fn reduced_runs_integrate_only_the_independent_species() {
+        let mut full = Simulator::new(&enzyme());
+        let expected = full.simulate(20.0, 0.05, "rk4").unwrap();
+        let mut reduced = Simulator::new(&enzyme());
+        reduced.set_conservation_reduction(true);
+        let results = reduced.simulate(20.0, 0.05, "rk4").unwrap();
+        
+        // ES and P are left once E and S are eliminated.
+        let last = &results.values[results.values.len() - 4..];
+        assert_eq!(reduced.integration_state().as_slice(), &last[2..]);
+        for (x, y) in results.values.iter().zip(&expected.values) {
+            assert!((x - y).abs() < 1e-9, "reduced {} vs full {}", x, y);
+        }
+    }
+}
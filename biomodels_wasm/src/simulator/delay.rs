@@ -0,0 +1,94 @@
+use nalgebra::DVector;
+
+use crate::math::{Expr, Scope};
+use crate::models::BioModelData;
+
+use super::Simulator;
+
+/// Which reactions have kinetic laws using the SBML `delay` csymbol.
+pub(crate) fn delayed_laws(model: &BioModelData) -> Vec<bool> {
+    model.reactions.iter()
+        .map(|r| r.kinetic_math.as_ref().is_some_and(Expr::contains_delay))
+        .collect()
+}
+
+/// Identifiers resolved against a state vector at a given time, with past
+/// values taken from the simulator's history.
+pub(crate) struct StateScope<'a> {
+    simulator: &'a Simulator,
+    t: f64,
+    state: &'a DVector<f64>,
+}
+
+impl Scope for StateScope<'_> {
+    fn value(&self, id: &str) -> Option<f64> {
+        let model = &self.simulator.model_ref;
+        if let Some(i) = model.get_species_index(id) {
+            return Some(self.state[i]);
+        }
+        if let Some(p) = model.parameters.iter().position(|p| p.id == id) {
+            return Some(self.simulator.parameter_at(p, self.t));
+        }
+        model.compartments.iter().find(|c| c.id == id).map(|c| c.size)
+    }
+    
+    fn time(&self) -> f64 {
+        self.t
+    }
+    
+    fn delayed(&self, expr: &Expr, tau: f64) -> f64 {
+        let t = self.t - tau.max(0.0);
+        let past = self.simulator.state_at(t);
+        expr.eval(&StateScope { simulator: self.simulator, t, state: &past })
+    }
+}
+
+impl Simulator {
+    pub(crate) fn scope<'a>(&'a self, t: f64, state: &'a DVector<f64>) -> StateScope<'a> {
+        StateScope { simulator: self, t, state }
+    }
+    
+    pub(crate) fn has_delays(&self) -> bool {
+        self.delayed_laws.iter().any(|&d| d)
+    }
+    
+    /// Appends the current state to the history used by delayed laws. Only
+    /// models with delays keep one.
+    pub(crate) fn record_history(&mut self) {
+        if self.has_delays() {
+            self.history.push((self.time, self.state.clone()));
+        }
+    }
+    
+    /// State at time `t` by linear interpolation of the recorded history.
+    ///
+    /// Before the first record the history is the initial state. Stages that
+    /// look past the last accepted step (delays shorter than the step size)
+    /// get the latest recorded state, which is the usual method-of-steps
+    /// restriction `tau >= dt`.
+    pub(crate) fn state_at(&self, t: f64) -> DVector<f64> {
+        let (first, last) = match (self.history.first(), self.history.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return self.initial_state(),
+        };
+        if t <= first.0 {
+            return first.1.clone();
+        }
+        if t >= last.0 {
+            return last.1.clone();
+        }
+        
+        let k = self.history.partition_point(|(ht, _)| *ht <= t);
+        let (t0, x0) = &self.history[k - 1];
+        let (t1, x1) = &self.history[k];
+        let w = (t - t0) / (t1 - t0);
+        x0 * (1.0 - w) + x1 * w
+    }
+    
+    fn initial_state(&self) -> DVector<f64> {
+        DVector::from_iterator(
+            self.state.len(),
+            self.model_ref.species.iter().map(|s| s.initial_concentration),
+        )
+    }
+}
@@ -0,0 +1,145 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+
+use super::Simulator;
+
+/// Default magnitude beyond which a value counts as blown up.
+pub const DEFAULT_DIVERGENCE_THRESHOLD: f64 = 1e12;
+
+/// A step that leaves a value this many times larger than the largest
+/// magnitude it started from, plus any dose it received, counts as
+/// explosive growth whatever the units.
+pub(crate) const GROWTH_FACTOR: f64 = 1e3;
+
+/// Reactions listed as contributing to the offending derivative.
+const TOP_CONTRIBUTIONS: usize = 5;
+
+/// Explicit RK4 is unstable once `dt · |λ|` exceeds roughly this.
+const EXPLICIT_STABILITY_LIMIT: f64 = 2.5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DivergenceReport {
+    /// Time at the end of the step that diverged.
+    pub time: f64,
+    pub species_id: String,
+    /// The non-finite, oversized or explosively growing value the step
+    /// produced.
+    pub value: f64,
+    /// Reactions with the largest `stoichiometry × rate` for the species at
+    /// the start of the failing step.
+    pub contributions: Vec<(String, f64)>,
+    pub suggestion: String,
+}
+
+impl Simulator {
+    pub fn set_divergence_threshold(&mut self, threshold: f64) {
+        self.divergence_threshold = threshold;
+    }
+    
+    /// Checks the state after a step ending at `t`; `previous` is the state
+    /// the step started from. Besides non-finite values and values above
+    /// the threshold, a value that grew by more than `GROWTH_FACTOR` in the
+    /// step diverges; forced species are exempt.
+    pub(crate) fn check_divergence(&self, previous: &DVector<f64>, t: f64, dt: f64, method: &str) -> Option<DivergenceReport> {
+        let scale = previous.amax();
+        let input = self.dose_input(t - dt, t);
+        let forced = self.forced_species();
+        let explosive = |i: usize, x: f64| {
+            let base = scale + input[i].abs();
+            base > 0.0 && x.abs() > GROWTH_FACTOR * base && !forced.contains(&i)
+        };
+        
+        let i = self.state.iter()
+            .enumerate()
+            .position(|(i, &x)| !x.is_finite() || x.abs() > self.divergence_threshold || explosive(i, x))?;
+        
+        let rates = self.compute_reaction_rates(previous);
+        let mut contributions: Vec<(String, f64)> = self.model_ref.reactions.iter()
+            .enumerate()
+            .map(|(j, r)| (r.id.clone(), self.stoichiometry_matrix[(i, j)] * rates[j]))
+            .filter(|(_, c)| *c != 0.0)
+            .collect();
+        contributions.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+        contributions.truncate(TOP_CONTRIBUTIONS);
+        
+        Some(DivergenceReport {
+            time: t,
+            species_id: self.model_ref.species[i].id.clone(),
+            value: self.state[i],
+            contributions,
+            suggestion: self.divergence_suggestion(previous, dt, method),
+        })
+    }
+    
+    fn divergence_suggestion(&self, previous: &DVector<f64>, dt: f64, method: &str) -> String {
+        if matches!(method, "ssa" | "ssa_direct" | "ssa_nrm" | "tau_leap" | "cle" | "hybrid") {
+            return "Populations grew without bound; check the rate constants of the listed reactions".to_string();
+        }
+        
+        let jacobian = self.jacobian(previous);
+        let fastest = jacobian.complex_eigenvalues().iter()
+            .map(|z| z.norm())
+            .fold(0.0, f64::max);
+        
+        if fastest.is_finite() && fastest * dt > EXPLICIT_STABILITY_LIMIT {
+            format!(
+                "Time step {} is too large for the fastest timescale ({:.3e}); the system is stiff here. \
+                 Reduce timeStep below {:.3e}",
+                dt, 1.0 / fastest, EXPLICIT_STABILITY_LIMIT / fastest
+            )
+        } else if method == "euler" {
+            "Explicit Euler is inaccurate here; try 'rk4' or a smaller timeStep".to_string()
+        } else {
+            "The model appears genuinely unstable; check the rate constants of the listed reactions".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing::mass_action;
+    use crate::simulator::SimulationError;
+    
+    #[test]
+    fn detects_blow_up_below_the_threshold() {
+        // dX/dt = 1e6 X² from 1e-6 blows up at t = 1 while X is still far
+        // below the magnitude threshold
+        let model = mass_action(&[("X", 1e-6)], &[("X X", "X X X", 1e6)]);
+        let mut sim = Simulator::new(&model);
+        
+        match sim.simulate(2.0, 0.01, "rk4") {
+            Err(SimulationError::Diverged(report)) => {
+                assert!(report.value.is_finite() && report.value.abs() < DEFAULT_DIVERGENCE_THRESHOLD, "{:?}", report);
+                assert!(report.time > 0.9 && report.time < 1.1, "{:?}", report);
+            }
+            other => panic!("expected divergence, got {:?}", other.map(|r| r.time.len())),
+        }
+    }
+    
+    #[test]
+    fn bounded_growth_from_zero_is_not_divergence() {
+        // A fast conversion into an empty species grows it manyfold at first
+        let model = mass_action(&[("A", 100.0), ("B", 0.0)], &[("A", "B", 50.0)]);
+        let mut sim = Simulator::new(&model);
+        
+        assert!(sim.simulate(1.0, 0.001, "rk4").is_ok());
+    }
+    
+    #[test]
+    fn a_large_dose_is_not_divergence() {
+        let model = mass_action(&[("X", 1e-3)], &[("X", "", 1.0)]);
+        let mut sim = Simulator::new(&model);
+        sim.add_dosing_schedule(crate::simulator::DosingSchedule {
+            target: "X".to_string(),
+            amount: 1e4,
+            start: 0.55,
+            interval: 0.0,
+            count: 1,
+            kind: crate::simulator::DoseKind::Bolus,
+            duration: None,
+        }).unwrap();
+        
+        assert!(sim.simulate(2.0, 0.1, "rk4").is_ok());
+    }
+}
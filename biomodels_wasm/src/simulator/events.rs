@@ -0,0 +1,110 @@
+use serde::{Serialize, Deserialize};
+
+use crate::timeline::GestationalTimeline;
+
+use super::dosing::DoseKind;
+use super::Simulator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A bolus added to its target species.
+    Dose,
+    /// An infusion starting or stopping; the assignment is the target's
+    /// total infusion rate.
+    InfusionStart,
+    InfusionEnd,
+    /// A parameter schedule switching value.
+    ScheduleChange,
+    /// A negative species set to zero.
+    PositivityClamp,
+    /// A step that went negative, discarded and retried with half the step
+    /// size; the assignment restores the species to its start-of-step value.
+    SolverRestart,
+    /// A gestational window opening or closing; no assignments.
+    WindowOpen,
+    WindowClose,
+}
+
+/// A variable changed by an event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Assignment {
+    pub variable: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+/// One entry of the event log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub time: f64,
+    pub kind: EventKind,
+    /// What fired: e.g. `APAP_maternal:dose3`, the parameter id of a
+    /// schedule, or the window name.
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assignments: Vec<Assignment>,
+}
+
+impl Simulator {
+    pub(crate) fn log_event(&mut self, time: f64, kind: EventKind, id: String, assignments: Vec<Assignment>) {
+        self.events.push(EventRecord { time, kind, id, assignments });
+    }
+    
+    /// Logs every infusion starting or ending and every scheduled parameter
+    /// change at `t`.
+    pub(crate) fn log_breakpoints(&mut self, t: f64, tolerance: f64) {
+        let at = |time: f64| (time - t).abs() <= tolerance;
+        let mut records = Vec::new();
+        
+        for schedule in self.dosing.iter().filter(|s| s.kind == DoseKind::Infusion) {
+            let (Some(duration), Some(i)) = (schedule.duration, self.model_ref.get_species_index(&schedule.target)) else {
+                continue;
+            };
+            let assignment = || Assignment {
+                variable: schedule.target.clone(),
+                before: self.infusion_rate(t - tolerance)[i],
+                after: self.infusion_rate(t)[i],
+            };
+            for (k, dose) in schedule.dose_times().enumerate() {
+                let id = format!("{}:infusion{}", schedule.target, k + 1);
+                if at(dose) {
+                    records.push((EventKind::InfusionStart, id.clone(), vec![assignment()]));
+                }
+                if at(dose + duration) {
+                    records.push((EventKind::InfusionEnd, id, vec![assignment()]));
+                }
+            }
+        }
+        
+        for schedule in &self.parameter_schedules {
+            if let Some(&(_, after)) = schedule.changes.iter().find(|(time, _)| at(*time)) {
+                let before = schedule.value_at(t - tolerance)
+                    .unwrap_or_else(|| self.model_ref.get_parameter_value(&schedule.parameter_id));
+                records.push((EventKind::ScheduleChange, schedule.parameter_id.clone(), vec![Assignment {
+                    variable: schedule.parameter_id.clone(),
+                    before,
+                    after,
+                }]));
+            }
+        }
+        
+        for (kind, id, assignments) in records {
+            self.log_event(t, kind, id, assignments);
+        }
+    }
+}
+
+/// Adds an entry for every window of `timeline` opening or closing within
+/// `[from, to]`, keeping the log in time order.
+pub fn log_windows(events: &mut Vec<EventRecord>, timeline: &GestationalTimeline, from: f64, to: f64) {
+    for window in &timeline.windows {
+        let (open, close) = (timeline.time_at_week(window.start_week), timeline.time_at_week(window.end_week));
+        for (time, kind) in [(open, EventKind::WindowOpen), (close, EventKind::WindowClose)] {
+            if (from..=to).contains(&time) {
+                events.push(EventRecord { time, kind, id: window.name.clone(), assignments: Vec::new() });
+            }
+        }
+    }
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+}
@@ -0,0 +1,72 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+
+use super::Simulator;
+
+/// Rate of one reaction at each output time, and its integral from the
+/// first output time.
+///
+/// The integral is accumulated by the trapezoidal rule over every
+/// integrator step, which are split at doses and parameter changes. For the
+/// stochastic methods it integrates the propensity, i.e. the expected
+/// number of firings, rather than counting them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReactionFlux {
+    pub reaction_id: String,
+    pub rates: Vec<f64>,
+    pub cumulative: Vec<f64>,
+}
+
+impl Simulator {
+    /// Whether runs record reaction fluxes.
+    pub fn set_record_fluxes(&mut self, enabled: bool) {
+        self.record_fluxes = enabled;
+    }
+    
+    /// Reaction rates of the current state at `t`, with forced species at
+    /// their series values.
+    pub(crate) fn current_rates(&self, t: f64) -> DVector<f64> {
+        let state = self.forced_state(t, &self.state);
+        self.compute_reaction_rates_at(t, &state)
+    }
+    
+    /// Rates before a step of `dt` from `self.time`, when fluxes are being
+    /// integrated.
+    pub(crate) fn flux_step_start(&self) -> Option<DVector<f64>> {
+        self.flux_totals.as_ref().map(|_| self.current_rates(self.time))
+    }
+    
+    /// Adds the trapezoidal integral of the rates over a step of `dt` that
+    /// started at `self.time` with rates `before`.
+    pub(crate) fn flux_step_end(&mut self, before: Option<DVector<f64>>, dt: f64) {
+        if let Some(before) = before {
+            let after = self.current_rates(self.time + dt);
+            if let Some(totals) = &mut self.flux_totals {
+                *totals += (before + after) * (dt / 2.0);
+            }
+        }
+    }
+    
+    pub(crate) fn empty_fluxes(&self, capacity: usize) -> Vec<ReactionFlux> {
+        if !self.record_fluxes {
+            return Vec::new();
+        }
+        self.model_ref.reactions.iter()
+            .map(|r| ReactionFlux {
+                reaction_id: r.id.clone(),
+                rates: Vec::with_capacity(capacity),
+                cumulative: Vec::with_capacity(capacity),
+            })
+            .collect()
+    }
+    
+    /// Appends the rates at `t` and the integrals so far.
+    pub(crate) fn sample_fluxes(&self, fluxes: &mut [ReactionFlux], t: f64) {
+        let Some(totals) = &self.flux_totals else { return };
+        let rates = self.current_rates(t);
+        for (j, flux) in fluxes.iter_mut().enumerate() {
+            flux.rates.push(rates[j]);
+            flux.cumulative.push(totals[j]);
+        }
+    }
+}
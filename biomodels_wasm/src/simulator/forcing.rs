@@ -0,0 +1,424 @@
+use std::borrow::Cow;
+
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+
+use crate::utils;
+
+use super::Simulator;
+use super::SimulationError;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// Each value holds until the next sample.
+    Step,
+    /// Piecewise cubic Hermite with Fritsch–Butland slopes: smooth, and
+    /// never overshoots the data between samples.
+    MonotoneCubic,
+}
+
+impl Interpolation {
+    pub fn parse(name: &str) -> Result<Interpolation, String> {
+        match name {
+            "linear" => Ok(Interpolation::Linear),
+            "step" => Ok(Interpolation::Step),
+            "monotone_cubic" => Ok(Interpolation::MonotoneCubic),
+            other => Err(format!("Unknown interpolation '{}' (expected linear, step or monotone_cubic)", other)),
+        }
+    }
+}
+
+/// What a forcing returns outside the sampled time range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Extrapolation {
+    /// The first or last sample.
+    #[default]
+    Hold,
+    /// Extends the first or last segment.
+    Linear,
+    Zero,
+    /// Fails the run before it starts.
+    Error,
+}
+
+/// A species or parameter bound to a sampled time series.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Forcing {
+    pub target: String,
+    pub times: Vec<f64>,
+    pub values: Vec<f64>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default)]
+    pub extrapolation: Extrapolation,
+}
+
+/// Options for reading a forcing from delimited text.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CsvForcingOptions {
+    pub target: String,
+    /// Column holding the values; defaults to the target id.
+    #[serde(default)]
+    pub column: Option<String>,
+    /// Column holding the times; defaults to the first column.
+    #[serde(default, rename = "timeColumn")]
+    pub time_column: Option<String>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default)]
+    pub extrapolation: Extrapolation,
+    /// Field separator; detected from the header when omitted.
+    #[serde(default)]
+    pub delimiter: Option<char>,
+}
+
+impl Forcing {
+    pub fn from_csv(text: &str, options: CsvForcingOptions) -> Result<Forcing, String> {
+        let (columns, rows) = utils::parse_table(text, options.delimiter)?;
+        let find = |name: &str| columns.iter()
+            .position(|c| c == name)
+            .ok_or_else(|| format!("Column '{}' not found (columns: {})", name, columns.join(", ")));
+        
+        let time_index = match &options.time_column {
+            Some(name) => find(name)?,
+            None => 0,
+        };
+        let value_index = find(options.column.as_deref().unwrap_or(&options.target))?;
+        
+        Ok(Forcing {
+            target: options.target,
+            times: rows.iter().map(|row| row[time_index]).collect(),
+            values: rows.iter().map(|row| row[value_index]).collect(),
+            interpolation: options.interpolation,
+            extrapolation: options.extrapolation,
+        })
+    }
+    
+    fn validate(&self) -> Result<(), String> {
+        if self.times.is_empty() || self.times.len() != self.values.len() {
+            return Err(format!(
+                "Forcing for '{}' needs matching, non-empty times and values ({} vs {})",
+                self.target, self.times.len(), self.values.len()
+            ));
+        }
+        if self.times.iter().chain(&self.values).any(|x| !x.is_finite()) {
+            return Err(format!("Forcing for '{}' contains non-finite numbers", self.target));
+        }
+        if self.times.windows(2).any(|w| w[1] <= w[0]) {
+            return Err(format!("Forcing times for '{}' must be strictly increasing", self.target));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ForcingTarget {
+    Species(usize),
+    Parameter(usize),
+}
+
+/// A validated forcing resolved against the model.
+#[derive(Debug, Clone)]
+pub(crate) struct BoundForcing {
+    pub(crate) forcing: Forcing,
+    pub(crate) target: ForcingTarget,
+    /// Knot slopes for monotone cubic interpolation.
+    slopes: Vec<f64>,
+}
+
+impl BoundForcing {
+    pub(crate) fn value_at(&self, t: f64) -> f64 {
+        let times = &self.forcing.times;
+        let values = &self.forcing.values;
+        let n = times.len();
+        let (first, last) = (times[0], times[n - 1]);
+        
+        if t < first || t > last {
+            match self.forcing.extrapolation {
+                Extrapolation::Zero => return 0.0,
+                Extrapolation::Linear if n > 1 => {
+                    let k = if t < first { 1 } else { n - 1 };
+                    return utils::interpolate(t, times[k - 1], times[k], values[k - 1], values[k]);
+                }
+                _ => return if t < first { values[0] } else { values[n - 1] },
+            }
+        }
+        interpolate_series(times, values, &self.slopes, self.forcing.interpolation, t)
+    }
+}
+
+/// Value at `t` of samples `values` at increasing `times`, with `t` inside
+/// the sampled range. `slopes` are the knot slopes from `monotone_slopes`
+/// and are only read for monotone cubic interpolation.
+pub(crate) fn interpolate_series(times: &[f64], values: &[f64], slopes: &[f64], interpolation: Interpolation, t: f64) -> f64 {
+    let n = times.len();
+    if n == 1 {
+        return values[0];
+    }
+    
+    let k = times.partition_point(|&x| x <= t).clamp(1, n - 1);
+    let (t0, t1, y0, y1) = (times[k - 1], times[k], values[k - 1], values[k]);
+    match interpolation {
+        Interpolation::Linear => utils::interpolate(t, t0, t1, y0, y1),
+        Interpolation::Step => if t >= t1 { y1 } else { y0 },
+        Interpolation::MonotoneCubic => {
+            let h = t1 - t0;
+            let s = (t - t0) / h;
+            let (s2, s3) = (s * s, s * s * s);
+            (2.0 * s3 - 3.0 * s2 + 1.0) * y0
+                + (s3 - 2.0 * s2 + s) * h * slopes[k - 1]
+                + (-2.0 * s3 + 3.0 * s2) * y1
+                + (s3 - s2) * h * slopes[k]
+        }
+    }
+}
+
+/// Fritsch–Butland slopes: a weighted harmonic mean of neighbouring secants
+/// where they agree in sign, zero at local extrema.
+pub(crate) fn monotone_slopes(times: &[f64], values: &[f64]) -> Vec<f64> {
+    let n = times.len();
+    if n < 2 {
+        return vec![0.0; n];
+    }
+    
+    let h: Vec<f64> = times.windows(2).map(|w| w[1] - w[0]).collect();
+    let secants: Vec<f64> = values.windows(2)
+        .zip(&h)
+        .map(|(w, h)| (w[1] - w[0]) / h)
+        .collect();
+    
+    let mut slopes = vec![0.0; n];
+    slopes[0] = secants[0];
+    slopes[n - 1] = secants[n - 2];
+    for k in 1..n - 1 {
+        let (d0, d1) = (secants[k - 1], secants[k]);
+        if d0 * d1 > 0.0 {
+            let (h0, h1) = (h[k - 1], h[k]);
+            slopes[k] = 3.0 * (h0 + h1) / ((2.0 * h1 + h0) / d0 + (h1 + 2.0 * h0) / d1);
+        }
+    }
+    slopes
+}
+
+impl Simulator {
+    /// Binds a species or parameter to a time series, replacing any earlier
+    /// forcing of the same target. Forced species follow the series exactly;
+    /// forced parameters are read by the kinetic laws that reference them,
+    /// which are then evaluated in full.
+    pub fn bind_forcing(&mut self, forcing: Forcing) -> Result<(), String> {
+        let bound = self.resolve_forcing(forcing)?;
+        if self.parameter_schedules.iter().any(|s| s.parameter_id == bound.forcing.target) {
+            return Err(format!("Parameter '{}' already has a schedule", bound.forcing.target));
+        }
+        self.forcings.retain(|f| f.target != bound.target);
+        self.forcings.push(bound);
+        Ok(())
+    }
+    
+    pub(crate) fn resolve_forcing(&self, forcing: Forcing) -> Result<BoundForcing, String> {
+        forcing.validate()?;
+        
+        let model = &self.model_ref;
+        let target = if let Some(i) = model.get_species_index(&forcing.target) {
+            ForcingTarget::Species(i)
+        } else if let Some(p) = model.parameters.iter().position(|p| p.id == forcing.target) {
+            ForcingTarget::Parameter(p)
+        } else {
+            return Err(format!("Forcing target '{}' is not a species or parameter of the model", forcing.target));
+        };
+        
+        let slopes = match forcing.interpolation {
+            Interpolation::MonotoneCubic => monotone_slopes(&forcing.times, &forcing.values),
+            _ => Vec::new(),
+        };
+        
+        Ok(BoundForcing { forcing, target, slopes })
+    }
+    
+    pub fn clear_forcings(&mut self) {
+        self.forcings.clear();
+    }
+    
+    pub fn forcings(&self) -> Vec<Forcing> {
+        self.forcings.iter().map(|f| f.forcing.clone()).collect()
+    }
+    
+    /// Fails if a forcing that may not extrapolate lacks data for part of
+    /// `[from, to]`.
+    pub(crate) fn check_forcing_range(&self, from: f64, to: f64) -> Result<(), SimulationError> {
+        for bound in &self.forcings {
+            let f = &bound.forcing;
+            if f.extrapolation != Extrapolation::Error {
+                continue;
+            }
+            let (first, last) = (f.times[0], f.times[f.times.len() - 1]);
+            if from < first || to > last {
+                return Err(SimulationError::ForcingOutOfRange {
+                    target: f.target.clone(),
+                    time: if from < first { from } else { to },
+                });
+            }
+        }
+        Ok(())
+    }
+    
+    pub(crate) fn is_forced_parameter(&self, p: usize) -> bool {
+        self.forcings.iter().any(|f| f.target == ForcingTarget::Parameter(p))
+    }
+    
+    /// Sample times of step forcings, where their values jump.
+    pub(crate) fn forcing_breakpoints(&self) -> impl Iterator<Item = f64> + '_ {
+        self.forcings.iter()
+            .filter(|f| f.forcing.interpolation == Interpolation::Step)
+            .flat_map(|f| f.forcing.times.iter().copied())
+    }
+    
+    /// Value of a forcing at `t`. Like a parameter schedule, a step forcing
+    /// is read at the middle of the current piece while a split step is
+    /// integrated, so a stage landing on a sample time does not see the
+    /// next value early.
+    fn forcing_value(&self, bound: &BoundForcing, t: f64) -> f64 {
+        match bound.forcing.interpolation {
+            Interpolation::Step => bound.value_at(self.schedule_time.unwrap_or(t)),
+            _ => bound.value_at(t),
+        }
+    }
+    
+    /// Value of parameter `p` at time `t`: its forcing, its schedule, or its
+    /// model value.
+    pub(crate) fn parameter_at(&self, p: usize, t: f64) -> f64 {
+        self.forcings.iter()
+            .find(|f| f.target == ForcingTarget::Parameter(p))
+            .map(|f| self.forcing_value(f, t))
+            .or_else(|| self.scheduled_value(p, t))
+            .unwrap_or(self.model_ref.parameters[p].value)
+    }
+    
+    /// `state` with forced species replaced by their series at `t`.
+    pub(crate) fn forced_state<'a>(&self, t: f64, state: &'a DVector<f64>) -> Cow<'a, DVector<f64>> {
+        let mut forced = Cow::Borrowed(state);
+        for bound in &self.forcings {
+            if let ForcingTarget::Species(i) = bound.target {
+                forced.to_mut()[i] = self.forcing_value(bound, t);
+            }
+        }
+        forced
+    }
+    
+    /// Zeroes the derivatives of forced species, which follow their series
+    /// instead of the reactions.
+    pub(crate) fn hold_forced_species(&self, derivatives: &mut DVector<f64>) {
+        for bound in &self.forcings {
+            if let ForcingTarget::Species(i) = bound.target {
+                derivatives[i] = 0.0;
+            }
+        }
+    }
+    
+    /// Resets forced species to their series at `t`.
+    pub(crate) fn apply_forcing(&mut self, t: f64) {
+        if let Cow::Owned(forced) = self.forced_state(t, &self.state) {
+            self.state = forced;
+        }
+    }
+    
+    /// Species whose values come from a forcing.
+    pub(crate) fn forced_species(&self) -> Vec<usize> {
+        self.forcings.iter()
+            .filter_map(|f| match f.target {
+                ForcingTarget::Species(i) => Some(i),
+                ForcingTarget::Parameter(_) => None,
+            })
+            .collect()
+    }
+    
+    /// Whether a forced species takes part in a conservation law; the
+    /// reduced system cannot hold it to its series.
+    pub(crate) fn forcing_crosses_conservation(&self, reduction: &super::conservation::Reduction) -> bool {
+        self.forcings.iter().any(|f| match f.target {
+            ForcingTarget::Species(i) => reduction.involves(i),
+            ForcingTarget::Parameter(_) => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing::{mass_action, with_law};
+    
+    const TIMES: [f64; 5] = [0.0, 1.0, 2.0, 4.0, 5.0];
+    const VALUES: [f64; 5] = [0.0, 0.0, 1.0, 1.0, 3.0];
+    
+    fn at(interpolation: Interpolation, t: f64) -> f64 {
+        let slopes = monotone_slopes(&TIMES, &VALUES);
+        interpolate_series(&TIMES, &VALUES, &slopes, interpolation, t)
+    }
+    
+    #[test]
+    fn every_interpolation_passes_through_the_samples() {
+        for interpolation in [Interpolation::Linear, Interpolation::Step, Interpolation::MonotoneCubic] {
+            for (&t, &y) in TIMES.iter().zip(&VALUES) {
+                assert!((at(interpolation, t) - y).abs() < 1e-12, "{:?} at {}", interpolation, t);
+            }
+        }
+    }
+    
+    #[test]
+    fn linear_and_step_between_samples() {
+        assert_eq!(at(Interpolation::Linear, 1.5), 0.5);
+        assert_eq!(at(Interpolation::Linear, 4.25), 1.5);
+        assert_eq!(at(Interpolation::Step, 1.5), 0.0);
+        assert_eq!(at(Interpolation::Step, 4.999), 1.0);
+    }
+    
+    #[test]
+    fn monotone_cubic_does_not_overshoot() {
+        let mut previous = 0.0;
+        for k in 0..=500 {
+            let t = k as f64 / 100.0;
+            let y = at(Interpolation::MonotoneCubic, t);
+            assert!(y >= previous - 1e-12, "decreases at {}", t);
+            if (2.0..=4.0).contains(&t) {
+                assert!((y - 1.0).abs() < 1e-12, "leaves the plateau at {}", t);
+            }
+            previous = y;
+        }
+    }
+    
+    #[test]
+    fn extrapolation_outside_the_samples() {
+        let sim = Simulator::new(&mass_action(&[("X", 0.0)], &[]));
+        let bound = |extrapolation| sim.resolve_forcing(Forcing {
+            target: "X".to_string(),
+            times: vec![1.0, 2.0],
+            values: vec![1.0, 3.0],
+            interpolation: Interpolation::Linear,
+            extrapolation,
+        }).unwrap();
+        assert_eq!(bound(Extrapolation::Hold).value_at(3.0), 3.0);
+        assert_eq!(bound(Extrapolation::Hold).value_at(0.0), 1.0);
+        assert_eq!(bound(Extrapolation::Linear).value_at(3.0), 5.0);
+        assert_eq!(bound(Extrapolation::Linear).value_at(0.0), -1.0);
+        assert_eq!(bound(Extrapolation::Zero).value_at(3.0), 0.0);
+    }
+    
+    #[test]
+    fn a_constant_parameter_forcing_reproduces_the_unforced_run() {
+        let model = with_law(mass_action(&[("X", 10.0)], &[("X", "", 1.0)]), 0, "k * X", &[("k", 0.5)]);
+        let baseline = Simulator::new(&model).simulate(5.0, 0.25, "rk4").unwrap();
+        let mut sim = Simulator::new(&model);
+        sim.bind_forcing(Forcing {
+            target: "k".to_string(),
+            times: vec![0.0, 2.0, 5.0],
+            values: vec![0.5; 3],
+            interpolation: Interpolation::Step,
+            extrapolation: Extrapolation::Hold,
+        }).unwrap();
+        assert_eq!(sim.simulate(5.0, 0.25, "rk4").unwrap().values, baseline.values);
+    }
+}
@@ -0,0 +1,149 @@
+use nalgebra::DVector;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+use super::Simulator;
+use super::stochastic::exponential;
+
+/// RK4 sub-steps per output step for the deterministic partition; the slow
+/// propensity integral is also accumulated at this resolution.
+const HYBRID_SUBSTEPS: usize = 10;
+
+/// Expected firings per output step above which a reaction is treated as
+/// fast when no explicit partition is given.
+pub const DEFAULT_HYBRID_THRESHOLD: f64 = 100.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridOptions {
+    /// Reaction ids integrated deterministically. When empty the split is
+    /// chosen automatically at every output step from propensity magnitude.
+    pub fast_reactions: Vec<String>,
+    pub threshold: f64,
+}
+
+impl Default for HybridOptions {
+    fn default() -> Self {
+        HybridOptions {
+            fast_reactions: Vec::new(),
+            threshold: DEFAULT_HYBRID_THRESHOLD,
+        }
+    }
+}
+
+/// How many output steps a reaction spent in each partition.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReactionPartition {
+    pub reaction_id: String,
+    pub deterministic_steps: usize,
+    pub stochastic_steps: usize,
+}
+
+impl Simulator {
+    pub fn set_hybrid_options(&mut self, options: HybridOptions) -> Result<(), String> {
+        for id in &options.fast_reactions {
+            if !self.model_ref.reactions.iter().any(|r| &r.id == id) {
+                return Err(format!("Reaction '{}' not found", id));
+            }
+        }
+        self.hybrid = options;
+        Ok(())
+    }
+    
+    pub(crate) fn reset_partitions(&mut self) {
+        self.partitions = self.model_ref.reactions.iter()
+            .map(|r| ReactionPartition {
+                reaction_id: r.id.clone(),
+                deterministic_steps: 0,
+                stochastic_steps: 0,
+            })
+            .collect();
+    }
+    
+    /// Advances the state by `dt`, integrating the fast reactions with RK4
+    /// and firing the slow ones stochastically. A slow reaction fires when
+    /// the integral of the total slow propensity along the continuous
+    /// trajectory crosses an exponentially distributed threshold.
+    pub(crate) fn hybrid_step(&mut self, dt: f64) {
+        let mut counts = self.state_to_counts();
+        let n_reactions = self.rate_constants.len();
+        
+        let fast: Vec<bool> = (0..n_reactions)
+            .map(|j| {
+                if self.hybrid.fast_reactions.is_empty() {
+                    self.propensity(j, &counts) * dt >= self.hybrid.threshold
+                } else {
+                    self.hybrid.fast_reactions.contains(&self.model_ref.reactions[j].id)
+                }
+            })
+            .collect();
+        
+        for (partition, &is_fast) in self.partitions.iter_mut().zip(&fast) {
+            if is_fast {
+                partition.deterministic_steps += 1;
+            } else {
+                partition.stochastic_steps += 1;
+            }
+        }
+        
+        let h = dt / HYBRID_SUBSTEPS as f64;
+        let mut integral = 0.0;
+        let mut threshold = exponential(&mut self.rng, 1.0);
+        
+        for _ in 0..HYBRID_SUBSTEPS {
+            let slow_before = self.slow_propensity(&counts, &fast);
+            
+            let k1 = self.fast_derivatives(&counts, &fast);
+            let k2 = self.fast_derivatives(&(&counts + &k1 * (h / 2.0)), &fast);
+            let k3 = self.fast_derivatives(&(&counts + &k2 * (h / 2.0)), &fast);
+            let k4 = self.fast_derivatives(&(&counts + &k3 * h), &fast);
+            counts += (k1 + k2 * 2.0 + k3 * 2.0 + k4) * (h / 6.0);
+            
+            integral += 0.5 * (slow_before + self.slow_propensity(&counts, &fast)) * h;
+            
+            while integral >= threshold {
+                let propensities: Vec<f64> = (0..n_reactions)
+                    .map(|j| if fast[j] { 0.0 } else { self.propensity(j, &counts) })
+                    .collect();
+                let a0: f64 = propensities.iter().sum();
+                if a0 <= 0.0 {
+                    break;
+                }
+                
+                let target = self.rng.gen::<f64>() * a0;
+                let mut cumulative = 0.0;
+                let mut chosen = n_reactions - 1;
+                for (j, a) in propensities.iter().enumerate() {
+                    cumulative += a;
+                    if cumulative > target {
+                        chosen = j;
+                        break;
+                    }
+                }
+                self.fire_reaction(chosen, &mut counts);
+                
+                integral -= threshold;
+                threshold = exponential(&mut self.rng, 1.0);
+            }
+        }
+        
+        self.state = self.counts_to_concentrations(&counts);
+    }
+    
+    fn fast_derivatives(&self, counts: &DVector<f64>, fast: &[bool]) -> DVector<f64> {
+        let mut derivatives = DVector::zeros(counts.len());
+        for (j, &is_fast) in fast.iter().enumerate() {
+            if is_fast {
+                derivatives += self.stoichiometry_matrix.column(j) * self.propensity(j, counts);
+            }
+        }
+        derivatives
+    }
+    
+    fn slow_propensity(&self, counts: &DVector<f64>, fast: &[bool]) -> f64 {
+        fast.iter()
+            .enumerate()
+            .filter(|(_, &is_fast)| !is_fast)
+            .map(|(j, _)| self.propensity(j, counts))
+            .sum()
+    }
+}
@@ -0,0 +1,154 @@
+use nalgebra::DVector;
+use rand_distr::{Distribution, Normal, Poisson};
+
+use super::Simulator;
+
+/// Error-control parameter of the Cao–Gillespie–Petzold tau selection.
+pub(crate) const TAU_EPSILON: f64 = 0.03;
+
+/// Leaps shorter than this many expected SSA steps are not worth it.
+const SSA_FALLBACK_FACTOR: f64 = 10.0;
+
+/// Number of exact SSA events fired whenever tau-leaping falls back.
+const SSA_FALLBACK_EVENTS: usize = 100;
+
+impl Simulator {
+    /// Advances the state by `dt` with explicit tau-leaping, choosing each
+    /// leap with the Cao–Gillespie–Petzold bound on relative propensity
+    /// change. Leaps that would drive a population negative are halved, and
+    /// when the leap shrinks to a handful of SSA steps exact SSA is used.
+    pub(crate) fn tau_leap_step(&mut self, dt: f64) {
+        let mut counts = self.whole_counts();
+        let n_reactions = self.rate_constants.len();
+        let mut s = 0.0;
+        
+        while s < dt {
+            let remaining = dt - s;
+            let propensities: Vec<f64> = (0..n_reactions)
+                .map(|j| self.propensity_at(j, self.time + s, &counts))
+                .collect();
+            let a0: f64 = propensities.iter().sum();
+            if a0 <= 0.0 {
+                break;
+            }
+            
+            let mut tau = self.select_tau(&counts, &propensities).min(remaining);
+            loop {
+                if tau < SSA_FALLBACK_FACTOR / a0 {
+                    s += self.direct_method_events(&mut counts, self.time + s, remaining, SSA_FALLBACK_EVENTS);
+                    break;
+                }
+                
+                let mut trial = counts.clone();
+                for (j, &a) in propensities.iter().enumerate() {
+                    if a > 0.0 {
+                        let firings = sample_poisson(&mut self.rng, a * tau);
+                        trial += self.stoichiometry_matrix.column(j) * firings;
+                    }
+                }
+                
+                if trial.iter().all(|&x| x >= 0.0) {
+                    counts = trial;
+                    s += tau;
+                    break;
+                }
+                tau /= 2.0;
+            }
+        }
+        
+        self.state = self.counts_to_concentrations(&counts);
+    }
+    
+    /// Advances the state by `dt` with Euler–Maruyama steps of the chemical
+    /// Langevin equation, treating counts as continuous. Each step takes all
+    /// propensities at its start and is limited by the same bound on
+    /// relative propensity change as tau-leaping.
+    pub(crate) fn langevin_step(&mut self, dt: f64) {
+        let mut counts = self.state_to_counts();
+        let normal = Normal::new(0.0, 1.0).expect("standard normal is valid");
+        let n_reactions = self.rate_constants.len();
+        let mut s = 0.0;
+        
+        while s < dt {
+            let propensities: Vec<f64> = (0..n_reactions)
+                .map(|j| self.propensity_at(j, self.time + s, &counts))
+                .collect();
+            if propensities.iter().all(|&a| a <= 0.0) {
+                break;
+            }
+            
+            let h = self.select_tau(&counts, &propensities).min(dt - s);
+            for (j, &a) in propensities.iter().enumerate() {
+                if a > 0.0 {
+                    let increment = a * h + (a * h).sqrt() * normal.sample(&mut self.rng);
+                    counts += self.stoichiometry_matrix.column(j) * increment;
+                }
+            }
+            s += h;
+        }
+        
+        self.state = self.counts_to_concentrations(&counts);
+    }
+    
+    fn select_tau(&self, counts: &DVector<f64>, propensities: &[f64]) -> f64 {
+        let mut tau = f64::INFINITY;
+        
+        for i in 0..counts.len() {
+            let g = self.highest_order(i, counts[i]);
+            if g == 0.0 {
+                continue;
+            }
+            
+            let mut mu = 0.0;
+            let mut sigma2 = 0.0;
+            for (j, &a) in propensities.iter().enumerate() {
+                let v = self.stoichiometry_matrix[(i, j)];
+                mu += v * a;
+                sigma2 += v * v * a;
+            }
+            
+            let bound = (TAU_EPSILON * counts[i] / g).max(1.0);
+            if mu != 0.0 {
+                tau = tau.min(bound / mu.abs());
+            }
+            if sigma2 > 0.0 {
+                tau = tau.min(bound * bound / sigma2);
+            }
+        }
+        
+        tau
+    }
+    
+    /// The `g_i` factor of Cao et al. (2006): the highest order of any
+    /// reaction consuming species `i`, corrected for reactions that consume
+    /// several copies of it. Zero when `i` is not a reactant anywhere.
+    fn highest_order(&self, i: usize, count: f64) -> f64 {
+        let species_id = &self.model_ref.species[i].id;
+        let x = count.max(3.0);
+        
+        self.model_ref.reactions.iter()
+            .filter_map(|reaction| {
+                let copies = reaction.reactants.iter().filter(|id| *id == species_id).count();
+                if copies == 0 {
+                    return None;
+                }
+                Some(match (reaction.reactants.len(), copies) {
+                    (1, _) => 1.0,
+                    (2, 1) => 2.0,
+                    (2, _) => 2.0 + 1.0 / (x - 1.0),
+                    (3, 1) => 3.0,
+                    (3, 2) => 1.5 * (2.0 + 1.0 / (x - 1.0)),
+                    (3, _) => 3.0 + 1.0 / (x - 1.0) + 2.0 / (x - 2.0),
+                    (order, _) => order as f64,
+                })
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+fn sample_poisson<R: rand::Rng>(rng: &mut R, mean: f64) -> f64 {
+    match Poisson::new(mean) {
+        Ok(poisson) => poisson.sample(rng),
+        Err(_) => 0.0,
+    }
+}
@@ -1,13 +1,114 @@
+use std::collections::BTreeMap;
+
 use nalgebra::{DVector, DMatrix};
 use serde::{Serialize, Deserialize};
 use wasm_bindgen::prelude::*;
 use crate::models::BioModelData;
-use web_sys;
+use crate::timeline::TimeUnit;
+use crate::utils::console_log;
+use thiserror::Error;
+
+pub mod checkpoint;
+pub mod stochastic;
+pub mod leaping;
+pub mod hybrid;
+pub mod steady_state;
+pub mod conservation;
+pub mod positivity;
+pub mod divergence;
+pub mod delay;
+pub mod dosing;
+pub mod forcing;
+pub mod schedule;
+pub mod variables;
+pub mod query;
+pub mod flux;
+pub mod observables;
+pub mod events;
+
+pub use checkpoint::{RunSettings, SimulatorState};
+pub use hybrid::{HybridOptions, ReactionPartition};
+pub use steady_state::{SteadyStateOptions, SteadyStateResult};
+pub use conservation::ConservationLaw;
+pub use positivity::{Positivity, PositivityIncident};
+pub use divergence::DivergenceReport;
+pub use dosing::{DosingSchedule, DoseKind};
+pub use forcing::{Forcing, CsvForcingOptions, Interpolation, Extrapolation};
+pub use schedule::ParameterSchedule;
+pub use variables::{VariableKind, VariableTrajectory};
+pub use flux::ReactionFlux;
+pub use observables::Observable;
+pub use events::{EventKind, EventRecord, Assignment};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+#[derive(Error, Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SimulationError {
+    #[error("Simulation diverged: {} = {} at t={}. {}", .0.species_id, .0.value, .0.time, .0.suggestion)]
+    Diverged(DivergenceReport),
+    #[error("{message}")]
+    NegativeConcentration { message: String },
+    #[error("Forcing for '{target}' has no data at t={time} and its extrapolation is 'error'")]
+    ForcingOutOfRange { target: String, time: f64 },
+}
 
+impl From<SimulationError> for JsValue {
+    fn from(error: SimulationError) -> JsValue {
+        let message = error.to_string();
+        match serde_wasm_bindgen::to_value(&error) {
+            Ok(value) => {
+                let _ = js_sys::Reflect::set(&value, &"message".into(), &message.into());
+                value
+            }
+            Err(_) => JsValue::from_str(&message),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Simulator {
+    time: f64,
     state: DVector<f64>,
     stoichiometry_matrix: DMatrix<f64>,
     rate_constants: Vec<f64>,
+    volumes: Vec<f64>,
+    seed: Option<u64>,
+    rng: ChaCha8Rng,
+    hybrid: HybridOptions,
+    partitions: Vec<ReactionPartition>,
+    reduce_conservation: bool,
+    reduction: Option<conservation::Reduction>,
+    positivity: Positivity,
+    incidents: Vec<PositivityIncident>,
+    divergence_threshold: f64,
+    delayed_laws: Vec<bool>,
+    /// Parameters referenced by each reaction's parsed kinetic law.
+    law_parameters: Vec<Vec<usize>>,
+    /// Reactant species and copies consumed, per reaction.
+    reactant_orders: Vec<Vec<(usize, u32)>>,
+    /// Reactions whose propensity changes when each reaction fires.
+    reaction_dependents: Vec<Vec<usize>>,
+    /// Molecules per unit of substance for the stochastic methods.
+    stochastic_scale: f64,
+    history: Vec<(f64, DVector<f64>)>,
+    dosing: Vec<DosingSchedule>,
+    forcings: Vec<forcing::BoundForcing>,
+    parameter_schedules: Vec<ParameterSchedule>,
+    /// Time scheduled parameters and step forcings are read at during a
+    /// split step.
+    schedule_time: Option<f64>,
+    /// Zero-order infusion input while a dosed step is being integrated.
+    input: Option<DVector<f64>>,
+    observables: Vec<observables::CompiledObservable>,
+    record_fluxes: bool,
+    /// Integral of each reaction rate since the start of the current run,
+    /// while fluxes are being recorded.
+    flux_totals: Option<DVector<f64>>,
+    /// Discontinuities since the last reset or output, in the order they
+    /// happened.
+    events: Vec<EventRecord>,
     model_ref: BioModelData,
 }
 
@@ -41,10 +142,43 @@ impl Simulator {
             .map(|r| r.rate_constant)
             .collect();
         
+        let volumes = model.species.iter()
+            .map(|s| model.get_species_volume(&s.id))
+            .collect();
+        
+        let reactant_orders = stochastic::reactant_orders(model);
+        let reaction_dependents = stochastic::dependency_graph(model, &reactant_orders, &stoichiometry);
+        
         Simulator {
+            time: 0.0,
             state,
             stoichiometry_matrix: stoichiometry,
             rate_constants,
+            volumes,
+            seed: None,
+            rng: ChaCha8Rng::from_entropy(),
+            hybrid: HybridOptions::default(),
+            partitions: Vec::new(),
+            reduce_conservation: false,
+            reduction: None,
+            positivity: Positivity::Clip,
+            incidents: Vec::new(),
+            divergence_threshold: divergence::DEFAULT_DIVERGENCE_THRESHOLD,
+            delayed_laws: delay::delayed_laws(model),
+            law_parameters: law_parameters(model),
+            reactant_orders,
+            reaction_dependents,
+            stochastic_scale: 1.0,
+            history: Vec::new(),
+            dosing: Vec::new(),
+            forcings: Vec::new(),
+            parameter_schedules: Vec::new(),
+            schedule_time: None,
+            input: None,
+            observables: Vec::new(),
+            record_fluxes: false,
+            flux_totals: None,
+            events: Vec::new(),
             model_ref: model.clone(),
         }
     }
@@ -54,103 +188,403 @@ impl Simulator {
         self.rate_constants = model.reactions.iter()
             .map(|r| r.rate_constant)
             .collect();
+        self.volumes = model.species.iter()
+            .map(|s| model.get_species_volume(&s.id))
+            .collect();
+        self.delayed_laws = delay::delayed_laws(model);
+        self.law_parameters = law_parameters(model);
+        self.reactant_orders = stochastic::reactant_orders(model);
+        self.reaction_dependents = stochastic::dependency_graph(model, &self.reactant_orders, &self.stoichiometry_matrix);
         // Also update initial state from the model
+        self.time = 0.0;
+        self.reduction = None;
         for (i, species) in model.species.iter().enumerate() {
             self.state[i] = species.initial_concentration;
         }
     }
     
-    pub fn simulate(&mut self, time_end: f64, time_step: f64, method: &str) -> Result<SimulationResults, JsValue> {
-        let num_steps = (time_end / time_step) as usize;
+    /// Changes one parameter in place without touching the current state,
+    /// for analyses that sweep a parameter along a solution branch.
+    pub fn set_parameter_value(&mut self, param_id: &str, value: f64) -> Result<(), String> {
+        let param = self.model_ref.parameters.iter_mut()
+            .find(|p| p.id == param_id)
+            .ok_or_else(|| format!("Parameter '{}' not found", param_id))?;
+        param.value = value;
+        Ok(())
+    }
+    
+    /// Fails when `param_id` is unknown or no parsed kinetic law references
+    /// it, since changing it would then leave every rate alone.
+    pub fn check_rate_parameter(&self, param_id: &str) -> Result<(), String> {
+        let p = self.model_ref.parameters.iter()
+            .position(|p| p.id == param_id)
+            .ok_or_else(|| format!("Parameter '{}' not found", param_id))?;
+        if !self.law_parameters.iter().any(|ps| ps.contains(&p)) {
+            return Err(format!("No kinetic law references parameter '{}'", param_id));
+        }
+        Ok(())
+    }
+    
+    /// Whether `param_id` reaches the run, through a parsed kinetic law or
+    /// an observable.
+    pub fn parameter_applies(&self, param_id: &str) -> bool {
+        let Some(p) = self.model_ref.parameters.iter().position(|p| p.id == param_id) else {
+            return false;
+        };
+        self.law_parameters.iter().any(|ps| ps.contains(&p))
+            || self.observables.iter().any(|o| o.expr.symbols().contains(&param_id))
+    }
+    
+    pub fn parameter_value(&self, param_id: &str) -> Option<f64> {
+        self.model_ref.parameters.iter()
+            .find(|p| p.id == param_id)
+            .map(|p| p.value)
+    }
+    
+    /// Seeds the random number generator used by the stochastic methods.
+    /// Without a seed each run draws fresh entropy.
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+    
+    pub fn simulate(&mut self, time_end: f64, time_step: f64, method: &str) -> Result<SimulationResults, SimulationError> {
+        self.reset_state();
+        self.advance(time_end, time_step, method)
+    }
+    
+    /// Integrates from the current time and state up to `time_end` without
+    /// resetting, so a run can be continued after `restore`.
+    pub fn advance(&mut self, time_end: f64, time_step: f64, method: &str) -> Result<SimulationResults, SimulationError> {
+        let num_steps = ((time_end - self.time) / time_step + 1e-9).max(0.0) as usize;
         let mut time_points = Vec::with_capacity(num_steps + 1);
         let mut values = Vec::with_capacity((num_steps + 1) * self.state.len());
         
-        self.reset_state();
+        self.check_forcing_range(self.time, time_end)?;
+        self.apply_forcing(self.time);
         
-        time_points.push(0.0);
-        values.extend_from_slice(self.state.as_slice());
+        let mut t = self.time;
+        self.reset_partitions();
+        self.incidents.clear();
+        // Unknown method names fall back to RK4 in `step`.
+        let deterministic = !matches!(method, "ssa" | "ssa_direct" | "ssa_nrm" | "tau_leap" | "cle" | "hybrid");
+        // A continued run keeps the totals it was already enforcing.
+        self.reduction = if self.reduce_conservation && deterministic {
+            Some(self.reduction.take().unwrap_or_else(|| self.build_reduction()))
+                .filter(|reduction| !self.infusions_cross_conservation(reduction))
+                .filter(|reduction| !self.forcing_crosses_conservation(reduction))
+        } else {
+            None
+        };
+        
+        let sources = self.tracked_variables();
+        let mut variables = self.empty_trajectories(&sources, num_steps + 1);
+        let mut fluxes = self.empty_fluxes(num_steps + 1);
+        self.flux_totals = self.record_fluxes.then(|| DVector::zeros(self.model_ref.reactions.len()));
         
-        let mut t = 0.0;
+        time_points.push(t);
+        values.extend_from_slice(self.state.as_slice());
+        self.sample_variables(&sources, &mut variables, t);
+        self.sample_fluxes(&mut fluxes, t);
+        if self.history.is_empty() {
+            self.record_history();
+        }
         
         for _ in 0..num_steps {
-            match method {
-                "euler" => self.euler_step(time_step),
-                "rk4" => self.runge_kutta4_step(time_step),
-                _ => self.runge_kutta4_step(time_step),
-            }
+            let previous = self.state.clone();
+            self.split_step(method, time_step, deterministic);
             
             t += time_step;
+            self.apply_forcing(t);
+            if let Some(report) = self.check_divergence(&previous, t, time_step, method) {
+                return Err(SimulationError::Diverged(report));
+            }
+            // Clipped values are the ones reported.
+            self.enforce_positivity(t)
+                .map_err(|message| SimulationError::NegativeConcentration { message })?;
+            
             time_points.push(t);
             values.extend_from_slice(self.state.as_slice());
+            self.sample_variables(&sources, &mut variables, t);
+            self.sample_fluxes(&mut fluxes, t);
             
-            for i in 0..self.state.len() {
-                if self.state[i] < 0.0 {
-                    self.state[i] = 0.0;
-                }
-            }
+            self.time = t;
+            self.record_history();
         }
         
-        web_sys::console::log_1(&format!("Simulation complete: {} time points generated", time_points.len()).into());
+        console_log(&format!("Simulation complete: {} time points generated", time_points.len()));
+        self.flux_totals = None;
+        let mut events = std::mem::take(&mut self.events);
+        events.sort_by(|a, b| a.time.total_cmp(&b.time));
         
         Ok(SimulationResults {
             time: time_points,
             values,
             species_names: self.model_ref.species.iter().map(|s| s.name.clone()).collect(),
+            species_ids: self.model_ref.species.iter().map(|s| s.id.clone()).collect(),
             num_species: self.state.len(),
+            reaction_partitions: if method == "hybrid" { Some(self.partitions.clone()) } else { None },
+            conservation_laws: self.reduction.as_ref().map(|r| r.conservation_laws(&self.model_ref)),
+            positivity_incidents: self.incidents.clone(),
+            dosing_schedules: self.dosing.clone(),
+            parameter_schedules: self.parameter_schedules.clone(),
+            variables,
+            fluxes,
+            events,
+            time_unit: None,
+            gestational_week: None,
+            run_info: Some(self.run_info(method, time_step, deterministic)),
+            warnings: self.rule_warnings(),
         })
     }
     
+    /// Solver settings of a run with `method` and `time_step`.
+    fn run_info(&self, method: &str, time_step: f64, deterministic: bool) -> RunInfo {
+        RunInfo {
+            method: Some(method.to_string()),
+            time_step: Some(time_step),
+            seed: self.seed,
+            positivity: Some(self.positivity.name().to_string()),
+            divergence_threshold: Some(self.divergence_threshold),
+            hybrid_threshold: (method == "hybrid").then_some(self.hybrid.threshold),
+            stochastic_scale: (!deterministic).then_some(self.stochastic_scale),
+            tolerances: self.tolerances(method),
+            parameter_overrides: Default::default(),
+        }
+    }
+    
+    /// The fixed tolerances and limits that shaped a run with `method`.
+    fn tolerances(&self, method: &str) -> BTreeMap<String, f64> {
+        let mut tolerances = BTreeMap::from([
+            ("breakpointTolerance".to_string(), dosing::TIME_TOLERANCE),
+            ("divergenceGrowthFactor".to_string(), divergence::GROWTH_FACTOR),
+        ]);
+        if method == "tau_leap" {
+            tolerances.insert("tauEpsilon".to_string(), leaping::TAU_EPSILON);
+        }
+        if self.positivity == Positivity::Reject {
+            tolerances.insert("maxStepHalvings".to_string(), positivity::MAX_HALVINGS as f64);
+        }
+        if self.reduction.is_some() {
+            tolerances.insert("conservationTolerance".to_string(), conservation::COEFFICIENT_TOLERANCE);
+        }
+        tolerances
+    }
+    
+    fn step(&mut self, method: &str, dt: f64) {
+        let rates_before = self.flux_step_start();
+        match method {
+            "euler" => self.euler_step(dt),
+            "rk4" => self.runge_kutta4_step(dt),
+            "ssa" => self.ssa_step(dt, stochastic::SsaVariant::Auto),
+            "ssa_direct" => self.ssa_step(dt, stochastic::SsaVariant::Direct),
+            "ssa_nrm" => self.ssa_step(dt, stochastic::SsaVariant::NextReaction),
+            "tau_leap" => self.tau_leap_step(dt),
+            "cle" => self.langevin_step(dt),
+            "hybrid" => self.hybrid_step(dt),
+            _ => self.runge_kutta4_step(dt),
+        }
+        self.flux_step_end(rates_before, dt);
+    }
+    
     fn reset_state(&mut self) {
+        self.time = 0.0;
+        self.rng = match self.seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+            None => ChaCha8Rng::from_entropy(),
+        };
         for (i, species) in self.model_ref.species.iter().enumerate() {
             self.state[i] = species.initial_concentration;
         }
+        self.history.clear();
+        self.events.clear();
+        self.reduction = None;
+        self.apply_initial_boluses();
     }
     
     fn compute_reaction_rates(&self, state: &DVector<f64>) -> DVector<f64> {
+        self.compute_reaction_rates_at(self.time, state)
+    }
+    
+    fn compute_reaction_rates_at(&self, t: f64, state: &DVector<f64>) -> DVector<f64> {
         let n_reactions = self.model_ref.reactions.len();
         let mut rates = DVector::zeros(n_reactions);
         
-        for (j, reaction) in self.model_ref.reactions.iter().enumerate() {
-            let mut rate = self.rate_constants[j];
-            
-            for reactant_id in &reaction.reactants {
-                if let Some(i) = self.model_ref.get_species_index(reactant_id) {
-                    rate *= state[i].max(0.0);
-                }
-            }
-            
-            rates[j] = rate;
+        for j in 0..n_reactions {
+            rates[j] = self.reaction_rate_at(j, t, state);
         }
         
         rates
     }
     
-    fn compute_derivatives(&self, state: &DVector<f64>) -> DVector<f64> {
-        let reaction_rates = self.compute_reaction_rates(state);
-        &self.stoichiometry_matrix * reaction_rates
+    /// Whether reaction `j` is evaluated from its kinetic law rather than by
+    /// mass action on its rate constant: every law whose MathML parsed is,
+    /// in every run, so that varying a parameter never changes which
+    /// expression a reaction uses.
+    pub(crate) fn evaluates_in_full(&self, j: usize) -> bool {
+        self.model_ref.reactions[j].kinetic_math.is_some()
+    }
+    
+    fn reaction_rate_at(&self, j: usize, t: f64, state: &DVector<f64>) -> f64 {
+        let reaction = &self.model_ref.reactions[j];
+        
+        // Parsed laws are evaluated in full; laws using unsupported
+        // constructs fall back to mass action on the rate constant.
+        if let Some(expr) = &reaction.kinetic_math {
+            return expr.eval(&self.scope(t, state));
+        }
+        
+        let mut rate = self.rate_constants[j];
+        
+        // Clipping keeps its historical zero floor on rates; the other
+        // strategies evaluate intermediate stages as they are so that mass
+        // balance holds and negatives are dealt with at the step level.
+        let floor = self.positivity == Positivity::Clip;
+        for reactant_id in &reaction.reactants {
+            if let Some(i) = self.model_ref.get_species_index(reactant_id) {
+                rate *= if floor { state[i].max(0.0) } else { state[i] };
+            }
+        }
+        
+        rate
+    }
+    
+    pub fn compute_derivatives(&self, state: &DVector<f64>) -> DVector<f64> {
+        self.compute_derivatives_at(self.time, state)
+    }
+    
+    pub fn compute_derivatives_at(&self, t: f64, state: &DVector<f64>) -> DVector<f64> {
+        let state = self.forced_state(t, state);
+        let reaction_rates = self.compute_reaction_rates_at(t, &state);
+        let mut derivatives = &self.stoichiometry_matrix * reaction_rates;
+        self.hold_forced_species(&mut derivatives);
+        derivatives
+    }
+    
+    /// Forward-difference Jacobian of the right-hand side at `state`.
+    pub fn jacobian(&self, state: &DVector<f64>) -> DMatrix<f64> {
+        let n = state.len();
+        let f0 = self.compute_derivatives(state);
+        let mut jacobian = DMatrix::zeros(n, n);
+        
+        for i in 0..n {
+            let h = f64::EPSILON.sqrt() * state[i].abs().max(1.0);
+            let mut perturbed = state.clone();
+            perturbed[i] += h;
+            let column = (self.compute_derivatives(&perturbed) - &f0) / h;
+            jacobian.set_column(i, &column);
+        }
+        
+        jacobian
     }
     
     fn euler_step(&mut self, dt: f64) {
-        let derivatives = self.compute_derivatives(&self.state);
-        self.state += derivatives * dt;
+        let y = self.integration_state();
+        let derivatives = self.integration_derivatives(self.time, &y);
+        self.set_integration_state(y + derivatives * dt);
     }
     
     fn runge_kutta4_step(&mut self, dt: f64) {
-        let k1 = self.compute_derivatives(&self.state);
-        let k2 = self.compute_derivatives(&(&self.state + &k1 * (dt / 2.0)));
-        let k3 = self.compute_derivatives(&(&self.state + &k2 * (dt / 2.0)));
-        let k4 = self.compute_derivatives(&(&self.state + &k3 * dt));
+        let t = self.time;
+        let y = self.integration_state();
+        let k1 = self.integration_derivatives(t, &y);
+        let k2 = self.integration_derivatives(t + dt / 2.0, &(&y + &k1 * (dt / 2.0)));
+        let k3 = self.integration_derivatives(t + dt / 2.0, &(&y + &k2 * (dt / 2.0)));
+        let k4 = self.integration_derivatives(t + dt, &(&y + &k3 * dt));
         
-        self.state += (k1 + k2 * 2.0 + k3 * 2.0 + k4) * (dt / 6.0);
+        self.set_integration_state(y + (k1 + k2 * 2.0 + k3 * 2.0 + k4) * (dt / 6.0));
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Indices of the global parameters each reaction's parsed kinetic law
+/// refers to.
+fn law_parameters(model: &BioModelData) -> Vec<Vec<usize>> {
+    model.reactions.iter()
+        .map(|r| match &r.kinetic_math {
+            Some(expr) => expr.symbols().into_iter()
+                .filter_map(|id| model.parameters.iter().position(|p| p.id == id))
+                .collect(),
+            None => Vec::new(),
+        })
+        .collect()
+}
+
+/// How a run was produced: recorded by the simulator in its results, and
+/// written to the Arrow schema metadata. Deserializes from the config
+/// object given to `simulate`; fields it does not know are ignored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, rename = "timeStep", skip_serializing_if = "Option::is_none")]
+    pub time_step: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub positivity: Option<String>,
+    #[serde(default, rename = "divergenceThreshold", skip_serializing_if = "Option::is_none")]
+    pub divergence_threshold: Option<f64>,
+    #[serde(default, rename = "hybridThreshold", skip_serializing_if = "Option::is_none")]
+    pub hybrid_threshold: Option<f64>,
+    #[serde(default, rename = "stochasticScale", skip_serializing_if = "Option::is_none")]
+    pub stochastic_scale: Option<f64>,
+    /// Solver tolerances and limits in effect for the run, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tolerances: BTreeMap<String, f64>,
+    /// Parameter values that differ from the model's, by parameter id.
+    #[serde(default, rename = "parameterOverrides")]
+    pub parameter_overrides: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SimulationResults {
     pub time: Vec<f64>,
     pub values: Vec<f64>,
     pub species_names: Vec<String>,
+    #[serde(default)]
+    pub species_ids: Vec<String>,
     pub num_species: usize,
+    /// Set by the hybrid method: which partition each reaction was in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reaction_partitions: Option<Vec<ReactionPartition>>,
+    /// Set when the run integrated the reduced system: the conservation laws
+    /// used to rebuild the dependent species.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conservation_laws: Option<Vec<ConservationLaw>>,
+    /// Every time a species went negative and how it was handled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub positivity_incidents: Vec<PositivityIncident>,
+    /// Dosing schedules in force during the run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dosing_schedules: Vec<DosingSchedule>,
+    /// Parameter schedules in force during the run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameter_schedules: Vec<ParameterSchedule>,
+    /// Compartments, non-constant parameters and observables at each time
+    /// point, for lookup by id alongside the species.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<VariableTrajectory>,
+    /// Per-reaction rates and their integrals, when the run recorded
+    /// fluxes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fluxes: Vec<ReactionFlux>,
+    /// Doses, infusion and schedule switches, positivity clamps, solver
+    /// restarts and gestational windows, in time order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<EventRecord>,
+    /// Unit of `time`, when the caller declared one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_unit: Option<TimeUnit>,
+    /// Gestational week at each time point, when a gestational start week
+    /// was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gestational_week: Option<Vec<f64>>,
+    /// Solver settings of the run, and the parameter overrides when it was
+    /// run through a `BioModel`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_info: Option<RunInfo>,
+    /// Parts of the model the run could not honour, such as SBML rules.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 impl SimulationResults {
@@ -161,4 +595,58 @@ impl SimulationResults {
         }
         trajectory
     }
-}
\ No newline at end of file
+    
+    /// Column index of a species, by id or else by name.
+    pub fn species_index(&self, species: &str) -> Option<usize> {
+        self.species_ids.iter()
+            .position(|id| id == species)
+            .or_else(|| self.species_names.iter().position(|name| name == species))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing::{mass_action, with_law};
+    
+    /// Decay by the law `k * X`, with a mass-action fallback constant that
+    /// deliberately differs from `k`.
+    fn decay() -> BioModelData {
+        with_law(mass_action(&[("X", 10.0)], &[("X", "", 1.0)]), 0, "k * X", &[("k", 0.5)])
+    }
+    
+    #[test]
+    fn parsed_laws_are_evaluated_in_every_run() {
+        let results = Simulator::new(&decay()).simulate(2.0, 0.01, "rk4").unwrap();
+        let last = results.values[results.values.len() - 1];
+        assert!((last - 10.0 * (-1.0_f64).exp()).abs() < 1e-8, "X(2) = {}", last);
+    }
+    
+    #[test]
+    fn setting_a_parameter_to_its_value_changes_nothing() {
+        let model = decay();
+        let baseline = Simulator::new(&model).simulate(5.0, 0.25, "rk4").unwrap();
+        let mut sim = Simulator::new(&model);
+        sim.set_parameter_value("k", 0.5).unwrap();
+        assert_eq!(sim.simulate(5.0, 0.25, "rk4").unwrap().values, baseline.values);
+    }
+    
+    #[test]
+    fn run_info_records_the_tolerances_that_applied() {
+        let mut sim = Simulator::new(&decay());
+        let ode = sim.simulate(1.0, 0.1, "rk4").unwrap().run_info.unwrap();
+        let leap = sim.simulate(1.0, 0.1, "tau_leap").unwrap().run_info.unwrap();
+        
+        assert!(ode.tolerances.contains_key("divergenceGrowthFactor"));
+        assert!(!ode.tolerances.contains_key("tauEpsilon"));
+        assert_eq!(leap.tolerances.get("tauEpsilon"), Some(&leaping::TAU_EPSILON));
+    }
+    
+    #[test]
+    fn only_referenced_parameters_apply() {
+        let model = with_law(mass_action(&[("X", 10.0)], &[("X", "", 1.0)]), 0, "k * X", &[("k", 0.5), ("unused", 1.0)]);
+        let sim = Simulator::new(&model);
+        
+        assert!(sim.parameter_applies("k"));
+        assert!(!sim.parameter_applies("unused"));
+    }
+}
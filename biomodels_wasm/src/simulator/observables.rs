@@ -0,0 +1,82 @@
+use serde::{Serialize, Deserialize};
+
+use crate::math::{self, Expr};
+use crate::parser::mathml;
+
+use super::Simulator;
+
+/// A named expression over species, parameters, compartments and `time`,
+/// evaluated at every output point. `expression` is infix text, or MathML
+/// when it starts with `<`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Observable {
+    pub id: String,
+    pub expression: String,
+}
+
+/// An observable with its parsed expression.
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledObservable {
+    pub(crate) observable: Observable,
+    pub(crate) expr: Expr,
+}
+
+impl Observable {
+    fn parse(&self) -> Result<Expr, String> {
+        let text = self.expression.trim();
+        if text.starts_with('<') {
+            mathml::parse_math_str(text)
+                .map_err(|e| format!("Observable '{}': {}", self.id, e))
+        } else {
+            math::parse_infix(text)
+                .map_err(|e| format!("Observable '{}': {}", self.id, e))
+        }
+    }
+}
+
+impl Simulator {
+    /// Replaces the observables; an empty list removes them. Every id an
+    /// expression uses must be a species, parameter or compartment of the
+    /// model, and observable ids must not clash with those.
+    pub fn set_observables(&mut self, observables: Vec<Observable>) -> Result<(), String> {
+        let model = &self.model_ref;
+        let is_model_id = |id: &str| {
+            model.get_species_index(id).is_some()
+                || model.parameters.iter().any(|p| p.id == id)
+                || model.compartments.iter().any(|c| c.id == id)
+        };
+        
+        let mut compiled: Vec<CompiledObservable> = Vec::with_capacity(observables.len());
+        for observable in observables {
+            if observable.id.is_empty() {
+                return Err("Observable ids must not be empty".to_string());
+            }
+            if is_model_id(&observable.id) || compiled.iter().any(|c| c.observable.id == observable.id) {
+                return Err(format!("Observable id '{}' is already in use", observable.id));
+            }
+            let expr = observable.parse()?;
+            if expr.contains_delay() {
+                return Err(format!("Observable '{}' uses delay, which observables do not support", observable.id));
+            }
+            if let Some(unknown) = expr.symbols().into_iter().find(|id| !is_model_id(id)) {
+                return Err(format!(
+                    "Observable '{}' refers to '{}', which is not a species, parameter or compartment",
+                    observable.id, unknown
+                ));
+            }
+            compiled.push(CompiledObservable { observable, expr });
+        }
+        
+        self.observables = compiled;
+        Ok(())
+    }
+    
+    pub fn observables(&self) -> Vec<Observable> {
+        self.observables.iter().map(|c| c.observable.clone()).collect()
+    }
+    
+    /// Value of observable `k` for the current state at `t`.
+    pub(crate) fn observable_value(&self, k: usize, t: f64) -> f64 {
+        self.observables[k].expr.eval(&self.scope(t, &self.state))
+    }
+}
@@ -0,0 +1,143 @@
+use serde::{Serialize, Deserialize};
+
+use super::events::{Assignment, EventKind};
+use super::Simulator;
+
+/// Maximum number of times a step is halved under `Positivity::Reject`
+/// before the remaining negatives are clipped.
+pub(crate) const MAX_HALVINGS: usize = 10;
+
+/// What to do when a step leaves a species negative.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Positivity {
+    /// Set negative values to zero and record it.
+    Clip,
+    /// Redo the step as two half steps, recursively, then clip as a last
+    /// resort.
+    Reject,
+    /// Abort the run.
+    Error,
+}
+
+impl Positivity {
+    pub fn parse(name: &str) -> Result<Positivity, String> {
+        match name {
+            "clip" => Ok(Positivity::Clip),
+            "reject" => Ok(Positivity::Reject),
+            "error" => Ok(Positivity::Error),
+            other => Err(format!("Unknown positivity strategy '{}' (expected clip, reject or error)", other)),
+        }
+    }
+    
+    pub fn name(self) -> &'static str {
+        match self {
+            Positivity::Clip => "clip",
+            Positivity::Reject => "reject",
+            Positivity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositivityIncident {
+    /// End of the output step for clips, start of the rejected step for
+    /// rejections.
+    pub time: f64,
+    pub species_id: String,
+    /// The negative value before it was handled.
+    pub value: f64,
+    /// "clipped", or "rejected" for a step that was retried with half the
+    /// step size.
+    pub action: String,
+}
+
+impl Simulator {
+    pub fn set_positivity(&mut self, positivity: Positivity) {
+        self.positivity = positivity;
+    }
+    
+    /// Takes one step of `dt` from `self.time`, halving it while it produces negative
+    /// values when the strategy is `Reject`.
+    pub(crate) fn positive_step(&mut self, method: &str, dt: f64, depth: usize) {
+        if self.positivity != Positivity::Reject {
+            self.step(method, dt);
+            return;
+        }
+        
+        let saved = self.state.clone();
+        let saved_fluxes = self.flux_totals.clone();
+        self.step(method, dt);
+        
+        if depth < MAX_HALVINGS {
+            if let Some(i) = self.state.iter().position(|&x| x < 0.0) {
+                self.incidents.push(PositivityIncident {
+                    time: self.time,
+                    species_id: self.model_ref.species[i].id.clone(),
+                    value: self.state[i],
+                    action: "rejected".to_string(),
+                });
+                let assignment = Assignment {
+                    variable: self.model_ref.species[i].id.clone(),
+                    before: self.state[i],
+                    after: saved[i],
+                };
+                let id = format!("restart:{}", assignment.variable);
+                self.log_event(self.time, EventKind::SolverRestart, id, vec![assignment]);
+                self.state = saved;
+                self.flux_totals = saved_fluxes;
+                let start = self.time;
+                self.positive_step(method, dt / 2.0, depth + 1);
+                self.time = start + dt / 2.0;
+                self.positive_step(method, dt / 2.0, depth + 1);
+                self.time = start;
+            }
+        }
+    }
+    
+    /// Applies the strategy to any negatives left after a step at time `t`.
+    pub(crate) fn enforce_positivity(&mut self, t: f64) -> Result<(), String> {
+        for i in 0..self.state.len() {
+            if self.state[i] < 0.0 {
+                if self.positivity == Positivity::Error {
+                    return Err(format!(
+                        "Species '{}' became negative ({}) at t={}",
+                        self.model_ref.species[i].id, self.state[i], t
+                    ));
+                }
+                self.incidents.push(PositivityIncident {
+                    time: t,
+                    species_id: self.model_ref.species[i].id.clone(),
+                    value: self.state[i],
+                    action: "clipped".to_string(),
+                });
+                let assignment = Assignment {
+                    variable: self.model_ref.species[i].id.clone(),
+                    before: self.state[i],
+                    after: 0.0,
+                };
+                let id = format!("positivity:{}", assignment.variable);
+                self.log_event(t, EventKind::PositivityClamp, id, vec![assignment]);
+                self.state[i] = 0.0;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing::mass_action;
+    
+    #[test]
+    fn clipped_runs_never_report_negative_values() {
+        // Euler with k·dt = 3 overshoots A to -2 on the first step
+        let model = mass_action(&[("A", 1.0), ("B", 0.0)], &[("A", "B", 3.0)]);
+        let mut sim = Simulator::new(&model);
+        sim.set_positivity(Positivity::Clip);
+        let results = sim.simulate(3.0, 1.0, "euler").unwrap();
+        
+        assert!(!results.positivity_incidents.is_empty());
+        assert!(results.values.iter().all(|&x| x >= 0.0), "{:?}", results.values);
+    }
+}
@@ -0,0 +1,119 @@
+use super::forcing::{interpolate_series, monotone_slopes, Interpolation};
+use super::{ReactionFlux, SimulationResults};
+
+impl SimulationResults {
+    /// Index into `variables`, by id or else by name.
+    pub fn variable_index(&self, variable: &str) -> Option<usize> {
+        self.variables.iter()
+            .position(|v| v.id == variable)
+            .or_else(|| self.variables.iter().position(|v| v.name == variable))
+    }
+    
+    /// Recorded rates and cumulative flux of a reaction.
+    pub fn flux(&self, reaction_id: &str) -> Option<&ReactionFlux> {
+        self.fluxes.iter().find(|f| f.reaction_id == reaction_id)
+    }
+    
+    /// Trajectory of a species or recorded variable, by id or else by name.
+    pub fn trajectory(&self, id: &str) -> Option<Vec<f64>> {
+        if let Some(i) = self.species_index(id) {
+            Some(self.get_species_trajectory(i))
+        } else {
+            self.variable_index(id).map(|v| self.variables[v].values.clone())
+        }
+    }
+    
+    /// Value of a species or variable at `t`, interpolated between output
+    /// points. `t` must lie within the simulated time range.
+    pub fn value_at(&self, id: &str, t: f64, interpolation: Interpolation) -> Result<f64, String> {
+        let values = self.trajectory(id)
+            .ok_or_else(|| format!("'{}' is not a species or variable in the results", id))?;
+        self.check_times()?;
+        let (first, last) = (self.time[0], self.time[self.time.len() - 1]);
+        if !(first..=last).contains(&t) {
+            return Err(format!("Time {} is outside the results' range [{}, {}]", t, first, last));
+        }
+        Ok(sample(&self.time, &values, interpolation, &[t])[0])
+    }
+    
+    /// The output points with `from <= t <= to`.
+    pub fn slice(&self, from: f64, to: f64) -> Result<SimulationResults, String> {
+        if from.is_nan() || to.is_nan() || from > to {
+            return Err(format!("Slice start {} must not be after its end {}", from, to));
+        }
+        let rows: Vec<usize> = (0..self.time.len())
+            .filter(|&i| (from..=to).contains(&self.time[i]))
+            .collect();
+        let pick = |column: &[f64]| rows.iter().map(|&i| column[i]).collect::<Vec<f64>>();
+        
+        let mut sliced = self.clone();
+        sliced.time = pick(&self.time);
+        sliced.values = rows.iter()
+            .flat_map(|&i| self.values[i * self.num_species..(i + 1) * self.num_species].iter().copied())
+            .collect();
+        for variable in &mut sliced.variables {
+            variable.values = pick(&variable.values);
+        }
+        for flux in &mut sliced.fluxes {
+            flux.rates = pick(&flux.rates);
+            flux.cumulative = pick(&flux.cumulative);
+        }
+        sliced.gestational_week = self.gestational_week.as_deref().map(pick);
+        sliced.positivity_incidents.retain(|incident| (from..=to).contains(&incident.time));
+        sliced.events.retain(|event| (from..=to).contains(&event.time));
+        Ok(sliced)
+    }
+    
+    /// Every column resampled onto a uniform grid from the first output time
+    /// in steps of `dt`, up to the last.
+    pub fn resample(&self, dt: f64, interpolation: Interpolation) -> Result<SimulationResults, String> {
+        if dt <= 0.0 || !dt.is_finite() {
+            return Err("Resampling step must be positive and finite".to_string());
+        }
+        self.check_times()?;
+        let (first, last) = (self.time[0], self.time[self.time.len() - 1]);
+        let count = ((last - first) / dt + 1e-9) as usize + 1;
+        let times: Vec<f64> = (0..count).map(|k| first + k as f64 * dt).collect();
+        
+        let columns: Vec<Vec<f64>> = (0..self.num_species)
+            .map(|i| sample(&self.time, &self.get_species_trajectory(i), interpolation, &times))
+            .collect();
+        
+        let mut resampled = self.clone();
+        resampled.values = (0..times.len())
+            .flat_map(|row| columns.iter().map(move |column| column[row]))
+            .collect();
+        for variable in &mut resampled.variables {
+            variable.values = sample(&self.time, &variable.values, interpolation, &times);
+        }
+        for flux in &mut resampled.fluxes {
+            flux.rates = sample(&self.time, &flux.rates, interpolation, &times);
+            flux.cumulative = sample(&self.time, &flux.cumulative, Interpolation::Linear, &times);
+        }
+        resampled.gestational_week = self.gestational_week.as_ref()
+            .map(|weeks| sample(&self.time, weeks, Interpolation::Linear, &times));
+        resampled.time = times;
+        Ok(resampled)
+    }
+    
+    fn check_times(&self) -> Result<(), String> {
+        if self.time.is_empty() {
+            return Err("Results have no time points".to_string());
+        }
+        if self.time.windows(2).any(|w| w[1] < w[0]) {
+            return Err("Result times must be non-decreasing".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Interpolates `values` at `times` onto each of `at`.
+fn sample(times: &[f64], values: &[f64], interpolation: Interpolation, at: &[f64]) -> Vec<f64> {
+    let slopes = match interpolation {
+        Interpolation::MonotoneCubic => monotone_slopes(times, values),
+        _ => Vec::new(),
+    };
+    at.iter()
+        .map(|&t| interpolate_series(times, values, &slopes, interpolation, t))
+        .collect()
+}
@@ -0,0 +1,180 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+
+use super::Simulator;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SteadyStateOptions {
+    /// Convergence threshold on the Euclidean norm of the right-hand side.
+    pub tolerance: f64,
+    pub max_iterations: usize,
+    /// Step size and horizon of the forward-integration fallback.
+    pub time_step: f64,
+    pub max_time: f64,
+    /// State to start from, in species order; the initial concentrations
+    /// when absent.
+    pub start: Option<Vec<f64>>,
+}
+
+impl Default for SteadyStateOptions {
+    fn default() -> Self {
+        SteadyStateOptions {
+            tolerance: 1e-8,
+            max_iterations: 100,
+            time_step: 1.0,
+            max_time: 1e5,
+            start: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SteadyStateResult {
+    pub species_ids: Vec<String>,
+    pub state: Vec<f64>,
+    pub residual_norm: f64,
+    pub converged: bool,
+    /// "newton" or "integration", whichever produced the returned state.
+    pub method: String,
+    pub iterations: usize,
+}
+
+impl Simulator {
+    /// Searches for an equilibrium starting from `options.start`, or else
+    /// from the initial concentrations (not the current state of a run).
+    ///
+    /// Damped Newton is tried first. The Jacobian is singular when the
+    /// network has conservation laws, so each step is a least-squares solve
+    /// with the conserved totals held fixed. If Newton stalls the system is
+    /// integrated forward with RK4 until the derivative norm drops below
+    /// tolerance.
+    pub fn steady_state(&self, options: &SteadyStateOptions) -> Result<SteadyStateResult, String> {
+        if !(options.time_step.is_finite() && options.time_step > 0.0) {
+            return Err(format!("Steady-state timeStep must be positive and finite, got {}", options.time_step));
+        }
+        if !(options.max_time.is_finite() && options.max_time >= 0.0) {
+            return Err(format!("Steady-state maxTime must be finite and non-negative, got {}", options.max_time));
+        }
+        let initial = match &options.start {
+            Some(start) if start.len() != self.state.len() => {
+                return Err(format!("Start state has {} values but the model has {} species", start.len(), self.state.len()));
+            }
+            Some(start) if start.iter().any(|x| !x.is_finite()) => {
+                return Err("Start state must be finite".to_string());
+            }
+            Some(start) => DVector::from_column_slice(start),
+            None => DVector::from_iterator(
+                self.state.len(),
+                self.model_ref.species.iter().map(|s| s.initial_concentration),
+            ),
+        };
+        
+        let (state, iterations) = self.newton_steady_state(initial.clone(), options);
+        if self.compute_derivatives(&state).norm() < options.tolerance {
+            return Ok(self.steady_state_result(state, options.tolerance, "newton", iterations));
+        }
+        
+        let (state, steps) = self.integrate_to_steady_state(initial, options);
+        Ok(self.steady_state_result(state, options.tolerance, "integration", steps))
+    }
+    
+    fn newton_steady_state(&self, mut state: DVector<f64>, options: &SteadyStateOptions) -> (DVector<f64>, usize) {
+        let mut residual = self.compute_derivatives(&state);
+        let conserved = self.left_null_space();
+        let n = state.len();
+        let m = conserved.ncols();
+        
+        for iteration in 0..options.max_iterations {
+            let norm = residual.norm();
+            if norm < options.tolerance {
+                return (state, iteration);
+            }
+            
+            let mut system = self.jacobian(&state).resize_vertically(n + m, 0.0);
+            system.view_mut((n, 0), (m, n)).copy_from(&conserved.transpose());
+            let rhs = (-&residual).resize_vertically(n + m, 0.0);
+            
+            let svd = system.svd(true, true);
+            let step = match svd.solve(&rhs, 1e-12) {
+                Ok(step) => step,
+                Err(_) => return (state, iteration),
+            };
+            
+            let mut damping = 1.0;
+            loop {
+                let candidate = &state + &step * damping;
+                if candidate.iter().all(|&x| x >= 0.0) {
+                    let candidate_residual = self.compute_derivatives(&candidate);
+                    if candidate_residual.norm() < norm {
+                        state = candidate;
+                        residual = candidate_residual;
+                        break;
+                    }
+                }
+                damping /= 2.0;
+                if damping < 1e-6 {
+                    return (state, iteration);
+                }
+            }
+        }
+        
+        (state, options.max_iterations)
+    }
+    
+    fn integrate_to_steady_state(&self, mut state: DVector<f64>, options: &SteadyStateOptions) -> (DVector<f64>, usize) {
+        let dt = options.time_step;
+        let max_steps = (options.max_time / dt) as usize;
+        
+        for step in 0..max_steps {
+            let k1 = self.compute_derivatives(&state);
+            if k1.norm() < options.tolerance {
+                return (state, step);
+            }
+            let k2 = self.compute_derivatives(&(&state + &k1 * (dt / 2.0)));
+            let k3 = self.compute_derivatives(&(&state + &k2 * (dt / 2.0)));
+            let k4 = self.compute_derivatives(&(&state + &k3 * dt));
+            state += (k1 + k2 * 2.0 + k3 * 2.0 + k4) * (dt / 6.0);
+            state.apply(|x| *x = x.max(0.0));
+        }
+        
+        (state, max_steps)
+    }
+    
+    fn steady_state_result(&self, state: DVector<f64>, tolerance: f64, method: &str, iterations: usize) -> SteadyStateResult {
+        let residual_norm = self.compute_derivatives(&state).norm();
+        SteadyStateResult {
+            species_ids: self.model_ref.species.iter().map(|s| s.id.clone()).collect(),
+            state: state.as_slice().to_vec(),
+            residual_norm,
+            converged: residual_norm < tolerance,
+            method: method.to_string(),
+            iterations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing::mass_action;
+    
+    #[test]
+    fn newton_finds_the_balance_of_production_and_decay() {
+        let model = mass_action(&[("X", 0.0)], &[("", "X", 2.0), ("X", "", 0.5)]);
+        let result = Simulator::new(&model).steady_state(&SteadyStateOptions::default()).unwrap();
+        assert!(result.converged);
+        assert_eq!(result.method, "newton");
+        assert!((result.state[0] - 4.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn newton_holds_conserved_totals() {
+        let model = mass_action(&[("A", 4.0), ("B", 0.0)], &[("A", "B", 1.0), ("B", "A", 3.0)]);
+        let result = Simulator::new(&model).steady_state(&SteadyStateOptions::default()).unwrap();
+        assert!(result.converged);
+        assert_eq!(result.method, "newton");
+        assert!((result.state[0] - 3.0).abs() < 1e-9);
+        assert!((result.state[1] - 1.0).abs() < 1e-9);
+    }
+}
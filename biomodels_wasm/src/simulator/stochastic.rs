@@ -0,0 +1,431 @@
+use nalgebra::{DMatrix, DVector};
+use rand::Rng;
+
+use crate::models::BioModelData;
+
+use super::Simulator;
+
+/// Above this many reactions `SsaVariant::Auto` switches from the direct
+/// method to the next-reaction method.
+const AUTO_NEXT_REACTION_THRESHOLD: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SsaVariant {
+    Auto,
+    Direct,
+    NextReaction,
+}
+
+/// Reactant species of each reaction with how many copies it consumes.
+pub(crate) fn reactant_orders(model: &BioModelData) -> Vec<Vec<(usize, u32)>> {
+    model.reactions.iter()
+        .map(|reaction| {
+            let mut orders: Vec<(usize, u32)> = Vec::new();
+            for i in reaction.reactants.iter().filter_map(|id| model.get_species_index(id)) {
+                match orders.iter_mut().find(|(k, _)| *k == i) {
+                    Some((_, order)) => *order += 1,
+                    None => orders.push((i, 1)),
+                }
+            }
+            orders
+        })
+        .collect()
+}
+
+/// For each reaction, the other reactions whose propensity changes when it
+/// fires: those reading a species whose count it changes. A parsed kinetic
+/// law reads every species it names, modifiers included; a mass-action
+/// fallback reads its reactants.
+pub(crate) fn dependency_graph(model: &BioModelData, orders: &[Vec<(usize, u32)>], stoichiometry: &DMatrix<f64>) -> Vec<Vec<usize>> {
+    let reads: Vec<Vec<usize>> = model.reactions.iter()
+        .zip(orders)
+        .map(|(reaction, orders)| match &reaction.kinetic_math {
+            Some(expr) => expr.symbols().into_iter()
+                .filter_map(|id| model.get_species_index(id))
+                .collect(),
+            None => orders.iter().map(|&(i, _)| i).collect(),
+        })
+        .collect();
+    
+    (0..reads.len())
+        .map(|j| {
+            (0..reads.len())
+                .filter(|&k| k != j)
+                .filter(|&k| reads[k].iter().any(|&i| stoichiometry[(i, j)] != 0.0))
+                .collect()
+        })
+        .collect()
+}
+
+impl Simulator {
+    /// Molecules per unit of substance (concentration times compartment
+    /// size) for the stochastic methods; 1 by default, so that counts are
+    /// the substance amounts themselves.
+    pub fn set_stochastic_scale(&mut self, scale: f64) -> Result<(), String> {
+        if !(scale.is_finite() && scale > 0.0) {
+            return Err(format!("Stochastic scale must be positive and finite, got {}", scale));
+        }
+        self.stochastic_scale = scale;
+        Ok(())
+    }
+    
+    /// Advances the state by `dt` with Gillespie's exact SSA.
+    ///
+    /// Concentrations are converted to molecule counts through the volume of
+    /// each species' compartment and the stochastic scale. Mass-action
+    /// propensities are combinatorial: a reaction consuming two copies of a
+    /// species goes with `x(x-1)`, so it cannot fire on a single molecule.
+    pub(crate) fn ssa_step(&mut self, dt: f64, variant: SsaVariant) {
+        let mut counts = self.whole_counts();
+        
+        let variant = match variant {
+            SsaVariant::Auto if self.rate_constants.len() > AUTO_NEXT_REACTION_THRESHOLD => SsaVariant::NextReaction,
+            SsaVariant::Auto => SsaVariant::Direct,
+            other => other,
+        };
+        
+        match variant {
+            SsaVariant::NextReaction => self.next_reaction_interval(&mut counts, dt),
+            _ => self.direct_method_interval(&mut counts, dt),
+        }
+        
+        self.state = self.counts_to_concentrations(&counts);
+    }
+    
+    pub(crate) fn state_to_counts(&self) -> DVector<f64> {
+        DVector::from_fn(self.state.len(), |i, _| self.state[i] * self.volumes[i] * self.stochastic_scale)
+    }
+    
+    /// The state as whole molecule counts. Fractional counts, from initial
+    /// conditions, doses or infusions, are rounded up with probability equal
+    /// to the fraction, so that rounding does not bias low populations.
+    pub(crate) fn whole_counts(&mut self) -> DVector<f64> {
+        let mut counts = self.state_to_counts();
+        for x in counts.iter_mut() {
+            let whole = x.floor();
+            let fraction = *x - whole;
+            *x = if fraction < 1e-9 {
+                whole
+            } else if fraction > 1.0 - 1e-9 || self.rng.gen::<f64>() < fraction {
+                whole + 1.0
+            } else {
+                whole
+            };
+        }
+        counts
+    }
+    
+    /// Propensity of reaction `j` at `counts`. Laws evaluated in full get
+    /// their rate from the concentrations times the reaction volume.
+    pub(crate) fn propensity(&self, j: usize, counts: &DVector<f64>) -> f64 {
+        self.propensity_at(j, self.time, counts)
+    }
+    
+    /// Propensity of reaction `j` at `counts` and time `t`.
+    pub(crate) fn propensity_at(&self, j: usize, t: f64, counts: &DVector<f64>) -> f64 {
+        let volume = self.reaction_volume(j) * self.stochastic_scale;
+        if self.evaluates_in_full(j) {
+            let concentrations = self.counts_to_concentrations(counts);
+            return self.reaction_rate_at(j, t, &concentrations) * volume;
+        }
+        
+        let mut a = self.rate_constants[j] * volume;
+        for &(i, order) in &self.reactant_orders[j] {
+            let scale = self.volumes[i] * self.stochastic_scale;
+            for m in 0..order {
+                a *= (counts[i] - m as f64).max(0.0) / scale;
+            }
+        }
+        a
+    }
+    
+    pub(crate) fn counts_to_concentrations(&self, counts: &DVector<f64>) -> DVector<f64> {
+        DVector::from_fn(counts.len(), |i, _| counts[i] / (self.volumes[i] * self.stochastic_scale))
+    }
+    
+    /// Volume a reaction happens in: that of its first reactant, or of its
+    /// first product for zero-order reactions.
+    pub(crate) fn reaction_volume(&self, j: usize) -> f64 {
+        let reaction = &self.model_ref.reactions[j];
+        reaction.reactants.iter()
+            .chain(reaction.products.iter())
+            .find_map(|id| self.model_ref.get_species_index(id))
+            .map(|i| self.volumes[i])
+            .unwrap_or(1.0)
+    }
+    
+    pub(crate) fn fire_reaction(&self, j: usize, counts: &mut DVector<f64>) {
+        *counts += self.stoichiometry_matrix.column(j);
+    }
+    
+    fn direct_method_interval(&mut self, counts: &mut DVector<f64>, dt: f64) {
+        self.direct_method_events(counts, self.time, dt, usize::MAX);
+    }
+    
+    /// Fires SSA events from time `start` until `horizon` is passed or
+    /// `max_events` have fired, returning how far the clock moved.
+    pub(crate) fn direct_method_events(&mut self, counts: &mut DVector<f64>, start: f64, horizon: f64, max_events: usize) -> f64 {
+        let n_reactions = self.rate_constants.len();
+        let mut propensities = vec![0.0; n_reactions];
+        let mut s = 0.0;
+        
+        for _ in 0..max_events {
+            for (j, a) in propensities.iter_mut().enumerate() {
+                *a = self.propensity_at(j, start + s, counts);
+            }
+            let a0: f64 = propensities.iter().sum();
+            if a0 <= 0.0 {
+                return horizon;
+            }
+            
+            let tau = exponential(&mut self.rng, a0);
+            if s + tau > horizon {
+                return horizon;
+            }
+            s += tau;
+            
+            let threshold = self.rng.gen::<f64>() * a0;
+            let mut cumulative = 0.0;
+            let mut chosen = n_reactions - 1;
+            for (j, a) in propensities.iter().enumerate() {
+                cumulative += a;
+                if cumulative > threshold {
+                    chosen = j;
+                    break;
+                }
+            }
+            
+            self.fire_reaction(chosen, counts);
+        }
+        
+        s
+    }
+    
+    /// Reactions whose propensity changes with time alone: laws reading the
+    /// clock or delayed values, or a parameter that a forcing drives.
+    fn clock_driven_reactions(&self) -> Vec<usize> {
+        let forced = |id: &str| self.model_ref.parameters.iter()
+            .position(|p| p.id == id)
+            .is_some_and(|p| self.forcings.iter().any(|f| f.target == super::forcing::ForcingTarget::Parameter(p)));
+        
+        self.model_ref.reactions.iter()
+            .enumerate()
+            .filter(|(_, r)| r.kinetic_math.as_ref().is_some_and(|expr| {
+                expr.contains_time() || expr.symbols().into_iter().any(forced)
+            }))
+            .map(|(j, _)| j)
+            .collect()
+    }
+    
+    /// Gibson–Bruck next-reaction method: putative firing times live in an
+    /// indexed priority queue. After each firing the reactions reading a
+    /// species it changed, and those that change with time alone, are
+    /// recomputed at the firing time.
+    fn next_reaction_interval(&mut self, counts: &mut DVector<f64>, dt: f64) {
+        let n_reactions = self.rate_constants.len();
+        let clock_driven = self.clock_driven_reactions();
+        
+        let mut propensities = vec![0.0; n_reactions];
+        let mut times = vec![f64::INFINITY; n_reactions];
+        for j in 0..n_reactions {
+            propensities[j] = self.propensity(j, counts);
+            if propensities[j] > 0.0 {
+                times[j] = exponential(&mut self.rng, propensities[j]);
+            }
+        }
+        let mut queue = IndexedMinHeap::new(times);
+        
+        loop {
+            let (mu, s) = queue.min();
+            if s > dt {
+                break;
+            }
+            
+            self.fire_reaction(mu, counts);
+            
+            let mut stale: Vec<usize> = self.reaction_dependents[mu].iter()
+                .chain(&clock_driven)
+                .copied()
+                .chain(std::iter::once(mu))
+                .collect();
+            stale.sort_unstable();
+            stale.dedup();
+            for k in stale {
+                let old = propensities[k];
+                let new = self.propensity_at(k, self.time + s, counts);
+                propensities[k] = new;
+                
+                let tau = if new <= 0.0 {
+                    f64::INFINITY
+                } else if k != mu && old > 0.0 {
+                    s + (old / new) * (queue.key(k) - s)
+                } else {
+                    s + exponential(&mut self.rng, new)
+                };
+                queue.update(k, tau);
+            }
+        }
+    }
+}
+
+pub(crate) fn exponential<R: Rng>(rng: &mut R, rate: f64) -> f64 {
+    // 1 - U lies in (0, 1], so the logarithm is always finite.
+    -(1.0 - rng.gen::<f64>()).ln() / rate
+}
+
+/// Binary min-heap over a fixed set of keys that supports changing the key
+/// of any entry in O(log n).
+struct IndexedMinHeap {
+    keys: Vec<f64>,
+    heap: Vec<usize>,
+    position: Vec<usize>,
+}
+
+impl IndexedMinHeap {
+    fn new(keys: Vec<f64>) -> Self {
+        let n = keys.len();
+        let mut queue = IndexedMinHeap {
+            keys,
+            heap: (0..n).collect(),
+            position: (0..n).collect(),
+        };
+        for i in (0..n / 2).rev() {
+            queue.sift_down(i);
+        }
+        queue
+    }
+    
+    fn min(&self) -> (usize, f64) {
+        match self.heap.first() {
+            Some(&j) => (j, self.keys[j]),
+            None => (0, f64::INFINITY),
+        }
+    }
+    
+    fn key(&self, j: usize) -> f64 {
+        self.keys[j]
+    }
+    
+    fn update(&mut self, j: usize, key: f64) {
+        self.keys[j] = key;
+        let i = self.position[j];
+        self.sift_up(i);
+        self.sift_down(self.position[j]);
+    }
+    
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.position[self.heap[a]] = a;
+        self.position[self.heap[b]] = b;
+    }
+    
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.keys[self.heap[i]] < self.keys[self.heap[parent]] {
+                self.swap(i, parent);
+                i = parent;
+            } else {
+                break;
+            }
+        }
+    }
+    
+    fn sift_down(&mut self, mut i: usize) {
+        let n = self.heap.len();
+        loop {
+            let left = 2 * i + 1;
+            let right = left + 1;
+            let mut smallest = i;
+            if left < n && self.keys[self.heap[left]] < self.keys[self.heap[smallest]] {
+                smallest = left;
+            }
+            if right < n && self.keys[self.heap[right]] < self.keys[self.heap[smallest]] {
+                smallest = right;
+            }
+            if smallest == i {
+                break;
+            }
+            self.swap(i, smallest);
+            i = smallest;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing::{mass_action, with_law};
+    
+    /// Mean over `runs` seeded replicates of first-order decay of `initial`
+    /// molecules, and the RK4 solution, at every output time.
+    fn decay_means(method: &str, initial: f64, runs: u64) -> (Vec<f64>, Vec<f64>) {
+        let model = mass_action(&[("X", initial)], &[("X", "", 0.5)]);
+        let mut sim = Simulator::new(&model);
+        let ode = sim.simulate(2.0, 0.25, "rk4").unwrap().values;
+        
+        let mut mean = vec![0.0; ode.len()];
+        for seed in 0..runs {
+            sim.set_seed(Some(seed));
+            let run = sim.simulate(2.0, 0.25, method).unwrap();
+            for (m, x) in mean.iter_mut().zip(&run.values) {
+                *m += x / runs as f64;
+            }
+        }
+        (mean, ode)
+    }
+    
+    fn assert_close(mean: &[f64], ode: &[f64], relative: f64) {
+        for (m, x) in mean.iter().zip(ode) {
+            assert!((m - x).abs() <= relative * x, "stochastic mean {} vs ODE {}", m, x);
+        }
+    }
+    
+    #[test]
+    fn ssa_mean_matches_the_ode_for_decay() {
+        let (mean, ode) = decay_means("ssa_direct", 1000.0, 100);
+        assert_close(&mean, &ode, 0.01);
+        let (mean, ode) = decay_means("ssa_nrm", 1000.0, 100);
+        assert_close(&mean, &ode, 0.01);
+    }
+    
+    #[test]
+    fn tau_leap_mean_matches_the_ode_for_decay() {
+        // Explicit leaps shrink the population by kτ instead of 1 - e^(-kτ),
+        // a bias of order the leap condition's 3%, on top of the noise.
+        let (mean, ode) = decay_means("tau_leap", 10000.0, 50);
+        assert_close(&mean, &ode, 0.03);
+    }
+    
+    /// X is made at rate `k * M` while M decays; M is a modifier of the
+    /// production, which consumes nothing.
+    fn modified_production() -> BioModelData {
+        with_law(mass_action(&[("M", 100.0), ("X", 0.0)], &[("", "X", 1.0), ("M", "", 1.0)]), 0, "k * M", &[("k", 0.2)])
+    }
+    
+    #[test]
+    fn laws_depend_on_every_species_they_read() {
+        let model = modified_production();
+        let sim = Simulator::new(&model);
+        
+        // M's decay changes the production rate, which reads M.
+        assert_eq!(sim.reaction_dependents[1], vec![0]);
+        assert!(sim.reaction_dependents[0].is_empty());
+    }
+    
+    #[test]
+    fn next_reaction_mean_follows_a_modifier() {
+        let model = modified_production();
+        let mut sim = Simulator::new(&model);
+        let ode = sim.simulate(3.0, 0.5, "rk4").unwrap();
+        let x = ode.values.len() - 1;
+        
+        let runs = 200;
+        let mut mean = 0.0;
+        for seed in 0..runs {
+            sim.set_seed(Some(seed));
+            mean += sim.simulate(3.0, 0.5, "ssa_nrm").unwrap().values[x] / runs as f64;
+        }
+        assert!((mean - ode.values[x]).abs() <= 0.05 * ode.values[x], "stochastic mean {} vs ODE {}", mean, ode.values[x]);
+    }
+}
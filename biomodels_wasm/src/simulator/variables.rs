@@ -0,0 +1,109 @@
+use serde::{Serialize, Deserialize};
+
+use crate::models::RuleKind;
+
+use super::Simulator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableKind {
+    Parameter,
+    Compartment,
+    Observable,
+}
+
+/// A model quantity other than a species, sampled at every output time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariableTrajectory {
+    pub id: String,
+    pub name: String,
+    pub kind: VariableKind,
+    pub values: Vec<f64>,
+    /// Set when an SBML rule defines the variable. Rules are not evaluated,
+    /// so the values are the declared ones rather than the rule's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<RuleKind>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum VariableSource {
+    Parameter(usize),
+    Compartment(usize),
+    Observable(usize),
+}
+
+impl Simulator {
+    /// Every compartment, every parameter that can change during a run
+    /// (declared non-constant, forced or scheduled) and every observable.
+    pub(crate) fn tracked_variables(&self) -> Vec<VariableSource> {
+        let parameters = self.model_ref.parameters.iter()
+            .enumerate()
+            .filter(|(p, param)| {
+                !param.constant
+                    || self.is_forced_parameter(*p)
+                    || self.parameter_schedules.iter().any(|s| s.parameter_id == param.id)
+            })
+            .map(|(p, _)| VariableSource::Parameter(p));
+        let compartments = (0..self.model_ref.compartments.len()).map(VariableSource::Compartment);
+        let observables = (0..self.observables.len()).map(VariableSource::Observable);
+        parameters.chain(compartments).chain(observables).collect()
+    }
+    
+    pub(crate) fn empty_trajectories(&self, sources: &[VariableSource], capacity: usize) -> Vec<VariableTrajectory> {
+        sources.iter()
+            .map(|source| {
+                let (id, name, kind) = match *source {
+                    VariableSource::Parameter(p) => {
+                        let id = &self.model_ref.parameters[p].id;
+                        (id, id, VariableKind::Parameter)
+                    }
+                    VariableSource::Compartment(c) => {
+                        let compartment = &self.model_ref.compartments[c];
+                        (&compartment.id, &compartment.name, VariableKind::Compartment)
+                    }
+                    VariableSource::Observable(k) => {
+                        let id = &self.observables[k].observable.id;
+                        (id, id, VariableKind::Observable)
+                    }
+                };
+                VariableTrajectory {
+                    rule: self.rule_kind(id),
+                    id: id.clone(),
+                    name: name.clone(),
+                    kind,
+                    values: Vec::with_capacity(capacity),
+                }
+            })
+            .collect()
+    }
+    
+    fn rule_kind(&self, id: &str) -> Option<RuleKind> {
+        self.model_ref.rules.iter()
+            .find(|rule| rule.variable == id)
+            .map(|rule| rule.kind)
+    }
+    
+    /// One warning per SBML rule, none of which are evaluated.
+    pub(crate) fn rule_warnings(&self) -> Vec<String> {
+        self.model_ref.rules.iter()
+            .map(|rule| {
+                let kind = match rule.kind {
+                    RuleKind::Assignment => "assignment",
+                    RuleKind::Rate => "rate",
+                };
+                format!("The {} rule for '{}' is not evaluated; its values ignore the rule", kind, rule.variable)
+            })
+            .collect()
+    }
+    
+    /// Appends each tracked variable's value at `t`.
+    pub(crate) fn sample_variables(&self, sources: &[VariableSource], variables: &mut [VariableTrajectory], t: f64) {
+        for (source, variable) in sources.iter().zip(variables) {
+            variable.values.push(match *source {
+                VariableSource::Parameter(p) => self.parameter_at(p, t),
+                VariableSource::Compartment(c) => self.model_ref.compartments[c].size,
+                VariableSource::Observable(k) => self.observable_value(k, t),
+            });
+        }
+    }
+}
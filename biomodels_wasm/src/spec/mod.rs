@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use crate::models::BioModelData;
+use crate::timeline::TimeUnit;
+
+/// A Synthea-style simulation config: which model to run, with which
+/// solver, for how long, from which inputs, and what to plot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationSpec {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// SBML file name, relative to the spec or to a models directory.
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default = "default_solver")]
+    pub solver: String,
+    #[serde(rename = "stepSize")]
+    pub step_size: f64,
+    pub duration: f64,
+    /// Initial concentrations and parameter values by id.
+    #[serde(default)]
+    pub inputs: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub charts: Vec<ChartSpec>,
+    /// Unit of `stepSize` and `duration`; the configs are written in hours.
+    #[serde(default, rename = "timeUnit")]
+    pub time_unit: TimeUnit,
+    #[serde(default, rename = "gestationalStartWeek")]
+    pub gestational_start_week: Option<f64>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChartSpec {
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default, rename = "axisParamX")]
+    pub axis_param_x: Option<String>,
+    #[serde(default, rename = "axisLabelX")]
+    pub axis_label_x: Option<String>,
+    #[serde(default, rename = "axisLabelY")]
+    pub axis_label_y: Option<String>,
+    #[serde(default)]
+    pub series: Vec<SeriesSpec>,
+    #[serde(default, rename = "logScaleX")]
+    pub log_scale_x: bool,
+    #[serde(default, rename = "logScaleY")]
+    pub log_scale_y: bool,
+    /// Size in pixels; 800 × 500 when omitted.
+    #[serde(default)]
+    pub width: Option<f64>,
+    #[serde(default)]
+    pub height: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeriesSpec {
+    /// Species or recorded variable to plot, by id or name.
+    pub param: String,
+    /// Legend text; defaults to `param`.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Any SVG colour; taken from a palette when omitted.
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+fn default_solver() -> String {
+    "runge_kutta".to_string()
+}
+
+impl SimulationSpec {
+    /// Parses a spec from YAML or JSON text.
+    pub fn parse(text: &str) -> Result<SimulationSpec, String> {
+        let spec: SimulationSpec = if text.trim_start().starts_with('{') {
+            serde_json::from_str(text).map_err(|e| format!("Invalid JSON spec: {}", e))?
+        } else {
+            serde_yaml::from_str(text).map_err(|e| format!("Invalid YAML spec: {}", e))?
+        };
+        spec.validate()?;
+        Ok(spec)
+    }
+    
+    fn validate(&self) -> Result<(), String> {
+        if !(self.step_size.is_finite() && self.step_size > 0.0) {
+            return Err(format!("stepSize must be positive, got {}", self.step_size));
+        }
+        if !(self.duration.is_finite() && self.duration >= 0.0) {
+            return Err(format!("duration must be non-negative, got {}", self.duration));
+        }
+        Ok(())
+    }
+    
+    /// Simulator method for `solver`, with a warning when the named solver
+    /// is only approximated.
+    pub fn method(&self) -> Result<(&'static str, Option<String>), String> {
+        let method = match self.solver.as_str() {
+            "runge_kutta" | "runge_kutta_4" | "rk4" => "rk4",
+            "euler" | "forward_euler" => "euler",
+            "dormand_prince_54" | "dormand_prince" => {
+                return Ok(("rk4", Some(format!(
+                    "Solver '{}' is not available; using fixed-step RK4 with stepSize {}",
+                    self.solver, self.step_size
+                ))));
+            }
+            "ssa" => "ssa",
+            "ssa_direct" => "ssa_direct",
+            "ssa_nrm" => "ssa_nrm",
+            "tau_leap" => "tau_leap",
+            "cle" => "cle",
+            "hybrid" => "hybrid",
+            other => return Err(format!("Unknown solver '{}'", other)),
+        };
+        Ok((method, None))
+    }
+    
+    /// Sets initial concentrations and parameter values from `inputs`,
+    /// failing without changes if any id is neither a species nor a
+    /// parameter, or is a parameter no parsed kinetic law references, as
+    /// setting it would leave the run unchanged.
+    pub fn apply_inputs(&self, model: &mut BioModelData) -> Result<(), String> {
+        let unknown: Vec<&str> = self.inputs.keys()
+            .filter(|id| model.get_species_index(id).is_none() && !model.parameters.iter().any(|p| &p.id == *id))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return Err(format!("Unknown input ids (not species or parameters): {}", unknown.join(", ")));
+        }
+        let inert: Vec<&str> = self.inputs.keys()
+            .filter(|id| model.get_species_index(id).is_none() && !model.parameter_in_laws(id))
+            .map(String::as_str)
+            .collect();
+        if !inert.is_empty() {
+            return Err(format!(
+                "Inputs for parameters that no parsed kinetic law references would not change the run: {}",
+                inert.join(", ")
+            ));
+        }
+        
+        for (id, value) in &self.inputs {
+            if let Some(i) = model.get_species_index(id) {
+                model.species[i].initial_concentration = *value;
+            } else if let Some(param) = model.parameters.iter_mut().find(|p| &p.id == id) {
+                param.value = *value;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Checks `model` against the SBML file the spec names, comparing file
+    /// stems with `source`, the file the model was loaded from, when known,
+    /// and otherwise with the model's id. A different source file is an
+    /// error; a different or missing id only gives a warning, as ids often
+    /// differ from file names.
+    pub fn check_model(&self, model: &BioModelData, source: Option<&str>) -> Result<Option<String>, String> {
+        let expected = match self.model.as_deref() {
+            Some(name) => file_stem(name),
+            None => return Ok(None),
+        };
+        
+        if let Some(source) = source {
+            let loaded = file_stem(source);
+            if loaded != expected {
+                return Err(format!("Spec is for model '{}' but the loaded model is '{}'", expected, loaded));
+            }
+            return Ok(None);
+        }
+        
+        Ok(match model.id.as_deref() {
+            Some(id) if id == expected => None,
+            Some(id) => Some(format!(
+                "Spec is for model '{}' but the loaded model's id is '{}'; pass the model file name to check it",
+                expected, id
+            )),
+            None => Some(format!(
+                "Spec is for model '{}' but the loaded model has no id; pass the model file name to check it",
+                expected
+            )),
+        })
+    }
+}
+
+fn file_stem(name: &str) -> &str {
+    Path::new(name).file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing::{mass_action, with_law};
+    
+    fn decay() -> BioModelData {
+        with_law(mass_action(&[("X", 10.0)], &[("X", "", 1.0)]), 0, "k * X", &[("k", 0.5), ("unused", 1.0)])
+    }
+    
+    #[test]
+    fn applies_inputs_that_reach_a_rate() {
+        let spec = SimulationSpec::parse("stepSize: 0.1\nduration: 1\ninputs:\n  X: 4\n  k: 2\n").unwrap();
+        let mut model = decay();
+        spec.apply_inputs(&mut model).unwrap();
+        assert_eq!(model.species[0].initial_concentration, 4.0);
+        assert_eq!(model.get_parameter_value("k"), 2.0);
+    }
+    
+    #[test]
+    fn rejects_parameters_no_law_references() {
+        let spec = SimulationSpec::parse("stepSize: 0.1\nduration: 1\ninputs:\n  k: 2\n  unused: 3\n").unwrap();
+        let mut model = decay();
+        let error = spec.apply_inputs(&mut model).unwrap_err();
+        assert!(error.contains("unused") && !error.contains("k,"), "{}", error);
+        assert_eq!(model.get_parameter_value("k"), 0.5);
+    }
+}
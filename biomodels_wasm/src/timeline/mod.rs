@@ -0,0 +1,144 @@
+use serde::{Serialize, Deserialize};
+
+pub const HOURS_PER_DAY: f64 = 24.0;
+pub const HOURS_PER_WEEK: f64 = 168.0;
+
+/// First gestational week of the second and third trimesters.
+const SECOND_TRIMESTER_WEEK: f64 = 14.0;
+const THIRD_TRIMESTER_WEEK: f64 = 28.0;
+
+/// Unit of a model's time axis. Deserialises through `parse`, so every
+/// spelling it accepts also works in configs and specs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum TimeUnit {
+    #[default]
+    Hour,
+    Day,
+    Week,
+}
+
+impl TimeUnit {
+    pub fn parse(name: &str) -> Result<TimeUnit, String> {
+        match name {
+            "hour" | "hours" | "h" => Ok(TimeUnit::Hour),
+            "day" | "days" | "d" => Ok(TimeUnit::Day),
+            "week" | "weeks" | "w" => Ok(TimeUnit::Week),
+            other => Err(format!("Unknown time unit '{}' (expected hour, day or week)", other)),
+        }
+    }
+    
+    pub fn hours(self) -> f64 {
+        match self {
+            TimeUnit::Hour => 1.0,
+            TimeUnit::Day => HOURS_PER_DAY,
+            TimeUnit::Week => HOURS_PER_WEEK,
+        }
+    }
+    
+    /// Converts `value` from this unit to `to`.
+    pub fn convert(self, value: f64, to: TimeUnit) -> f64 {
+        value * self.hours() / to.hours()
+    }
+}
+
+impl TryFrom<String> for TimeUnit {
+    type Error = String;
+    
+    fn try_from(name: String) -> Result<TimeUnit, String> {
+        TimeUnit::parse(&name)
+    }
+}
+
+/// A named span of gestation, e.g. a critical period, in gestational weeks.
+/// `end_week` is exclusive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GestationalWindow {
+    pub name: String,
+    #[serde(rename = "startWeek")]
+    pub start_week: f64,
+    #[serde(rename = "endWeek")]
+    pub end_week: f64,
+}
+
+/// Maps simulation time to gestational age.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GestationalTimeline {
+    /// Unit of simulation time.
+    #[serde(default, rename = "timeUnit")]
+    pub time_unit: TimeUnit,
+    /// Gestational week at simulation time 0.
+    #[serde(default, rename = "startWeek")]
+    pub start_week: f64,
+    #[serde(default)]
+    pub windows: Vec<GestationalWindow>,
+}
+
+/// Where a simulation time falls in gestation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GestationalPoint {
+    pub week: f64,
+    pub trimester: u8,
+    /// Names of the windows containing this week.
+    pub windows: Vec<String>,
+}
+
+impl GestationalTimeline {
+    pub fn new(time_unit: TimeUnit, start_week: f64) -> Self {
+        GestationalTimeline {
+            time_unit,
+            start_week,
+            windows: Vec::new(),
+        }
+    }
+    
+    pub fn week(&self, t: f64) -> f64 {
+        self.start_week + self.time_unit.convert(t, TimeUnit::Week)
+    }
+    
+    /// Simulation time at which gestational week `week` begins.
+    pub fn time_at_week(&self, week: f64) -> f64 {
+        TimeUnit::Week.convert(week - self.start_week, self.time_unit)
+    }
+    
+    /// Trimester containing time `t`: 1 before week 14, 2 before week 28,
+    /// 3 after.
+    pub fn trimester(&self, t: f64) -> u8 {
+        let week = self.week(t);
+        if week < SECOND_TRIMESTER_WEEK {
+            1
+        } else if week < THIRD_TRIMESTER_WEEK {
+            2
+        } else {
+            3
+        }
+    }
+    
+    pub fn active_windows(&self, t: f64) -> Vec<String> {
+        let week = self.week(t);
+        self.windows.iter()
+            .filter(|w| w.start_week <= week && week < w.end_week)
+            .map(|w| w.name.clone())
+            .collect()
+    }
+    
+    pub fn point(&self, t: f64) -> GestationalPoint {
+        GestationalPoint {
+            week: self.week(t),
+            trimester: self.trimester(t),
+            windows: self.active_windows(t),
+        }
+    }
+    
+    /// Simulation-time bounds of a named window.
+    pub fn window_times(&self, name: &str) -> Option<(f64, f64)> {
+        self.windows.iter()
+            .find(|w| w.name == name)
+            .map(|w| (self.time_at_week(w.start_week), self.time_at_week(w.end_week)))
+    }
+    
+    /// Gestational week for every time point.
+    pub fn weeks(&self, times: &[f64]) -> Vec<f64> {
+        times.iter().map(|&t| self.week(t)).collect()
+    }
+}
@@ -6,6 +6,15 @@ pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
+/// Writes `message` to the browser console. Native builds have no console,
+/// so the message is dropped there.
+pub fn console_log(message: &str) {
+    #[cfg(target_arch = "wasm32")]
+    web_sys::console::log_1(&message.into());
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = message;
+}
+
 pub fn interpolate(x: f64, x0: f64, x1: f64, y0: f64, y1: f64) -> f64 {
     y0 + (y1 - y0) * (x - x0) / (x1 - x0)
 }
@@ -18,4 +27,101 @@ pub fn clamp(value: f64, min: f64, max: f64) -> f64 {
     } else {
         value
     }
-}
\ No newline at end of file
+}
+
+/// Parses delimited numeric text with a header row into column names and
+/// rows. `delimiter` is detected from the header when not given: whichever
+/// of comma, tab or semicolon comes first outside quotes. Fields may be quoted,
+/// with `""` standing for a quote, but may not span lines. Blank lines and
+/// lines starting with `#` are skipped. Empty cells and `NA` read as NaN.
+pub fn parse_table(text: &str, delimiter: Option<char>) -> Result<(Vec<String>, Vec<Vec<f64>>), String> {
+    let mut lines = text.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+    
+    let (_, header) = lines.next().ok_or_else(|| "Table is empty".to_string())?;
+    let delimiter = match delimiter {
+        Some(d) => d,
+        None => first_delimiter(header).unwrap_or(','),
+    };
+    let columns: Vec<String> = split_fields(header, delimiter)
+        .map_err(|e| format!("Line 1: {}", e))?
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .collect();
+    
+    let mut rows = Vec::new();
+    for (index, line) in lines {
+        let row = split_fields(line, delimiter)
+            .map_err(|e| format!("Line {}: {}", index + 1, e))?
+            .iter()
+            .map(|cell| match cell.trim() {
+                "" | "NA" | "N/A" => Ok(f64::NAN),
+                cell => cell.parse::<f64>(),
+            })
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|e| format!("Line {}: {}", index + 1, e))?;
+        if row.len() != columns.len() {
+            return Err(format!("Line {}: expected {} columns, found {}", index + 1, columns.len(), row.len()));
+        }
+        rows.push(row);
+    }
+    
+    Ok((columns, rows))
+}
+
+/// The comma, tab or semicolon that comes first outside quotes in
+/// `header`.
+fn first_delimiter(header: &str) -> Option<char> {
+    let mut quoted = false;
+    header.chars().find(|&c| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        !quoted && matches!(c, ',' | '\t' | ';')
+    })
+}
+
+/// Splits one line of delimited text into fields, unquoting quoted ones.
+fn split_fields(line: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+        } else if c == '"' && field.trim().is_empty() {
+            field.clear();
+            quoted = true;
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut field));
+        } else {
+            field.push(c);
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// `field` as written to delimited text: quoted, with quotes doubled, when
+/// it contains the delimiter, a quote or a line break.
+pub fn quote_field(field: &str, delimiter: &str) -> String {
+    if field.contains(delimiter) || field.contains(['"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}